@@ -0,0 +1,116 @@
+// timings of a life cycle, run with
+// cargo test --release bench -- --ignored --nocapture
+
+use std::collections::HashSet;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Life;
+use crate::Universe;
+use crate::Vector;
+
+// the cycle before neighbor counts were accumulated in a single pass:
+// 3^N - 1 lookups for every live cell and for every empty neighbor
+fn lookup_cycle<const N: usize>(life: &mut Life<N>) {
+    let mut empty = HashSet::new();
+    for c in life.cells.iter() {
+        for d in life.neighbors.iter() {
+            let pos = crate::vec_add(c, d);
+            if !life.cells.contains(&pos) {
+                empty.insert(pos);
+            }
+        }
+    }
+
+    let born = empty
+        .into_iter()
+        .filter(|pos| life.rule.born(life.count_neighbors(pos)));
+    let mut next: HashSet<_> = life
+        .cells
+        .iter()
+        .filter(|c| life.rule.survives(life.count_neighbors(c)))
+        .copied()
+        .collect();
+    next.extend(born);
+
+    life.cells = next;
+}
+
+// the rod in the xy plane, advanced a few generations so there is
+// something to count in every dimension
+fn seed<const N: usize>(generations: usize) -> Life<N> {
+    let mut life = Life::<N>::new();
+    for y in -1..=1 {
+        let mut pos: Vector<N> = [0; N];
+        pos[1] = y;
+        life.create(pos);
+    }
+
+    life.step_n(generations as u64);
+
+    life
+}
+
+fn time(f: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+fn compare<const N: usize>(generations: usize) {
+    let mut scatter = seed::<N>(generations);
+    let mut lookup = seed::<N>(generations);
+    let population = scatter.cells.len();
+
+    let t_scatter = time(|| scatter.cycle());
+    let t_lookup = time(|| lookup_cycle(&mut lookup));
+    assert_eq!(scatter.cells, lookup.cells);
+
+    println!(
+        "N = {}, {:>5} cells: scatter {:>10.3?}, lookup {:>10.3?}, {:.1}x",
+        N,
+        population,
+        t_scatter,
+        t_lookup,
+        t_lookup.as_secs_f64() / t_scatter.as_secs_f64()
+    );
+}
+
+#[test]
+#[ignore]
+fn bench_cycle() {
+    compare::<2>(20);
+    compare::<3>(6);
+    compare::<4>(4);
+    compare::<5>(3);
+    compare::<6>(2);
+    compare::<7>(2);
+}
+
+fn compare_parallel<const N: usize>(generations: usize) {
+    let mut serial = seed::<N>(generations);
+    let mut parallel = seed::<N>(generations);
+    parallel.set_threads(0);
+    let population = serial.cells.len();
+
+    let t_serial = time(|| serial.cycle());
+    let t_parallel = time(|| parallel.cycle());
+    assert_eq!(serial.cells, parallel.cells);
+
+    println!(
+        "N = {}, {:>5} cells: serial {:>10.3?}, parallel {:>10.3?}, {:.1}x",
+        N,
+        population,
+        t_serial,
+        t_parallel,
+        t_serial.as_secs_f64() / t_parallel.as_secs_f64()
+    );
+}
+
+#[test]
+#[ignore]
+fn bench_parallel() {
+    compare_parallel::<4>(6);
+    compare_parallel::<5>(4);
+    compare_parallel::<6>(3);
+}
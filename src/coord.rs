@@ -0,0 +1,88 @@
+//! integer types usable as coordinates and the error for leaving their
+//! range
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// integer type of the coordinates, i16 keeps small universes compact and
+/// i64 lets patterns travel for a long time
+/// anything in between is done in i64, which every coordinate fits into
+pub trait Coord:
+    Copy + Ord + Hash + Debug + Default + Send + Sync + Into<i64> + TryFrom<i64> + 'static
+{
+    /// the smallest coordinate
+    const MIN: Self;
+    /// the largest coordinate
+    const MAX: Self;
+
+    /// the sum, None if it overflows
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    /// the difference, None if it overflows
+    fn checked_sub(self, other: Self) -> Option<Self>;
+
+    /// the coordinate as an i64, which every coordinate fits into
+    fn to_i64(self) -> i64 {
+        self.into()
+    }
+
+    /// None if x is outside the range of the type
+    fn from_i64(x: i64) -> Option<Self> {
+        Self::try_from(x).ok()
+    }
+}
+
+macro_rules! impl_coord {
+    ($($t:ty),*) => {
+        $(
+            impl Coord for $t {
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+
+                fn checked_sub(self, other: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_coord!(i16, i32, i64);
+
+/// a cell would leave the range of the coordinate type
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutOfRange {
+    pos: Vec<i64>,
+}
+
+impl OutOfRange {
+    /// the error for a cell that is too close to the edge of the range
+    pub fn new<C: Coord>(pos: &[C]) -> Self {
+        OutOfRange {
+            pos: pos.iter().map(|&x| x.to_i64()).collect(),
+        }
+    }
+
+    /// the cell that is too close to the edge of the range
+    pub fn position(&self) -> &[i64] {
+        &self.pos
+    }
+}
+
+impl fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the pattern would leave the coordinate range at {:?}",
+            self.pos
+        )
+    }
+}
+
+impl Error for OutOfRange {}
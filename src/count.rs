@@ -0,0 +1,235 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+use crate::coord::Coord;
+use crate::pool;
+use crate::topology::Topology;
+use crate::Vector;
+
+// count the live neighbors of every position that has at least one, each
+// live cell adds one to the cells it is a neighbor of, which for offset d
+// are the sources of c, just c - d unless the topology folds
+pub fn scatter<const N: usize, C: Coord>(
+    cells: &HashSet<Vector<N, C>>,
+    offsets: &[Vector<N, C>],
+    topology: &Topology<N>,
+) -> HashMap<Vector<N, C>, usize> {
+    let mut counts = HashMap::with_capacity(cells.len() * 4);
+
+    for c in cells.iter() {
+        for d in offsets.iter() {
+            for pos in topology.sources(c, d) {
+                *counts.entry(pos).or_insert(0) += 1;
+            }
+        }
+    }
+
+    counts
+}
+
+// the shard a position belongs to when the counts are split up
+pub fn shard<const N: usize, C: Coord>(pos: &Vector<N, C>, shards: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    pos.hash(&mut hasher);
+    (hasher.finish() % shards as u64) as usize
+}
+
+// the same counts as scatter, computed on several threads and split into
+// shards by the position's hash, the count of pos is in shards[shard(pos)]
+// the live cells are cut into chunks that are scattered into per-shard
+// maps, then the maps of each shard are merged
+pub fn scatter_parallel<const N: usize, C: Coord>(
+    cells: &HashSet<Vector<N, C>>,
+    offsets: &[Vector<N, C>],
+    topology: &Topology<N>,
+    threads: usize,
+) -> Vec<HashMap<Vector<N, C>, usize>> {
+    let shards = threads * 4;
+    let cells: Vec<_> = cells.iter().collect();
+    let chunk = cells.len().div_ceil(threads * 4).max(1);
+    let chunks: Vec<_> = cells.chunks(chunk).collect();
+
+    let scattered = pool::run(threads, chunks.len(), |i| {
+        let mut counts = vec![HashMap::new(); shards];
+        for c in chunks[i].iter() {
+            for d in offsets.iter() {
+                for pos in topology.sources(c, d) {
+                    *counts[shard(&pos, shards)].entry(pos).or_insert(0) += 1;
+                }
+            }
+        }
+        counts
+    });
+
+    pool::run(threads, shards, |s| {
+        let mut merged: HashMap<Vector<N, C>, usize> = HashMap::new();
+        for counts in scattered.iter() {
+            for (&pos, &n) in counts[s].iter() {
+                *merged.entry(pos).or_insert(0) += n;
+            }
+        }
+        merged
+    })
+}
+
+// count the live cells in the (2r+1)^N box around every position that has
+// at least one live cell in its box, the center cell included
+// the box sum is separable, so it is done as N passes of 1D sliding window
+// sums, costing about N * (2r+1) operations per cell instead of (2r+1)^N
+pub fn box_sums<const N: usize, C: Coord>(
+    cells: &HashSet<Vector<N, C>>,
+    r: i64,
+) -> HashMap<Vector<N, C>, usize> {
+    let mut sums: HashMap<Vector<N, C>, usize> = cells.iter().map(|&c| (c, 1)).collect();
+
+    for axis in 0..N {
+        sums = window_sums(&sums, axis, r);
+    }
+
+    sums
+}
+
+// count the live cells along the axes up to distance r from every position
+// that has one, the center cell included once
+// each axis is a 1D window sum that includes the center, so the center is
+// taken out of all but one of them
+pub fn cross_sums<const N: usize, C: Coord>(
+    cells: &HashSet<Vector<N, C>>,
+    r: i64,
+) -> HashMap<Vector<N, C>, usize> {
+    let ones: HashMap<Vector<N, C>, usize> = cells.iter().map(|&c| (c, 1)).collect();
+    let mut sums: HashMap<Vector<N, C>, usize> = HashMap::new();
+
+    for axis in 0..N {
+        for (pos, n) in window_sums(&ones, axis, r) {
+            *sums.entry(pos).or_insert(0) += n;
+        }
+    }
+    for c in cells.iter() {
+        *sums.get_mut(c).unwrap() -= N - 1;
+    }
+
+    sums
+}
+
+// sum the values in a window of radius r along one axis
+fn window_sums<const N: usize, C: Coord>(
+    values: &HashMap<Vector<N, C>, usize>,
+    axis: usize,
+    r: i64,
+) -> HashMap<Vector<N, C>, usize> {
+    // group the values into lines parallel to the axis
+    let mut lines: HashMap<Vector<N, C>, Vec<(i64, usize)>> = HashMap::new();
+    for (pos, &v) in values.iter() {
+        let mut key = *pos;
+        key[axis] = C::default();
+        lines.entry(key).or_default().push((pos[axis].to_i64(), v));
+    }
+
+    let mut sums = HashMap::with_capacity(values.len());
+
+    for (key, mut line) in lines {
+        line.sort_unstable();
+
+        // sweep the window over every position within r of a value,
+        // lo..hi is the range of values inside the window
+        let (mut lo, mut hi) = (0, 0);
+        let mut sum = 0;
+        let mut next = i64::MIN;
+
+        for &(x, _) in line.iter() {
+            for p in next.max(x - r)..=x + r {
+                while hi < line.len() && line[hi].0 <= p + r {
+                    sum += line[hi].1;
+                    hi += 1;
+                }
+                while line[lo].0 < p - r {
+                    sum -= line[lo].1;
+                    lo += 1;
+                }
+
+                let mut pos = key;
+                pos[axis] = C::from_i64(p).expect("coordinate overflow");
+                sums.insert(pos, sum);
+            }
+            next = x + r + 1;
+        }
+    }
+
+    sums
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::neighborhood::Neighborhood;
+
+    #[test]
+    fn scatter_counts() {
+        let cells = HashSet::from([[0, 0, 0], [1, 0, 0], [0, -1, -1]]);
+        let offsets = Neighborhood::Moore(1).offsets();
+        let counts = scatter(&cells, &offsets, &Topology::unbounded());
+
+        assert_eq!(counts[&[0, 0, 0]], 2);
+        assert_eq!(counts[&[1, 0, 0]], 2);
+        assert_eq!(counts[&[2, 0, 0]], 1);
+        assert_eq!(counts.get(&[3, 0, 0]), None);
+    }
+
+    #[test]
+    fn parallel_scatter_counts() {
+        let cells: HashSet<_> = (0..50).map(|i| [i % 7, i / 7 - i % 3, i % 5]).collect();
+        let offsets = Neighborhood::Moore(1).offsets();
+        let topology = Topology::unbounded();
+        let serial = scatter(&cells, &offsets, &topology);
+        let shards = scatter_parallel(&cells, &offsets, &topology, 3);
+
+        assert_eq!(shards.iter().map(|s| s.len()).sum::<usize>(), serial.len());
+        for (pos, n) in serial.iter() {
+            assert_eq!(shards[shard(pos, shards.len())][pos], *n);
+        }
+    }
+
+    #[test]
+    fn box_sums_match_direct_count() {
+        let cells: HashSet<Vector<2, i64>> =
+            HashSet::from([[0, 0], [1, 0], [5, 2], [-3, 4], [2, 2]]);
+        let r = 2;
+        let sums = box_sums(&cells, r);
+
+        for x in -6..=8 {
+            for y in -3..=7 {
+                let direct = cells
+                    .iter()
+                    .filter(|c| (c[0] - x).abs() <= r && (c[1] - y).abs() <= r)
+                    .count();
+                assert_eq!(sums.get(&[x, y]).copied().unwrap_or(0), direct);
+            }
+        }
+    }
+
+    #[test]
+    fn cross_sums_match_direct_count() {
+        let cells: HashSet<Vector<3, i64>> =
+            HashSet::from([[0, 0, 0], [1, 0, 0], [3, 2, 0], [-2, 0, 1], [0, 0, -3]]);
+        let r = 3;
+        let sums = cross_sums(&cells, r);
+
+        for x in -6..=7 {
+            for y in -4..=6 {
+                for z in -7..=5 {
+                    let direct = cells
+                        .iter()
+                        .map(|c| [c[0] - x, c[1] - y, c[2] - z])
+                        .filter(|d| d.iter().filter(|&&v| v != 0).count() <= 1)
+                        .filter(|d| d.iter().all(|v| v.abs() <= r))
+                        .count();
+                    assert_eq!(sums.get(&[x, y, z]).copied().unwrap_or(0), direct);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,473 @@
+//! universes whose number of dimensions is only known at runtime
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::coord::OutOfRange;
+use crate::neighborhood::Neighborhood;
+use crate::rule::Rule;
+use crate::universe::Universe;
+use crate::Life;
+use crate::Vector;
+
+/// position with as many coordinates as the universe has dimensions
+pub type DynVector = Vec<i32>;
+
+/// sparse infinite life where the number of dimensions is only known at
+/// runtime, like for patterns loaded from a file
+/// the coordinates are heap allocated so it is slower than Life, AnyLife
+/// uses Life for the common dimensions instead
+/// Generations rules are supported the same way as in Life
+///
+/// unstable, this can change in any release
+pub struct DynLife {
+    dims: usize,
+    cells: HashSet<DynVector>,
+    decaying: HashMap<DynVector, u8>, // states above 1 of Generations rules
+    neighbors: Vec<DynVector>,
+    rule: Rule,
+    generation: u64,
+}
+
+impl DynLife {
+    /// an empty universe with the given number of axes, playing the
+    /// original game
+    pub fn new(dims: usize) -> Self {
+        Self::with_rule(dims, Rule::default())
+    }
+
+    /// an empty universe with another rule on the Moore neighborhood
+    pub fn with_rule(dims: usize, rule: Rule) -> Self {
+        Self::with_neighborhood(dims, rule, Neighborhood::default())
+    }
+
+    /// an empty universe with another rule and neighborhood
+    /// panics if there are no axes
+    pub fn with_neighborhood(dims: usize, rule: Rule, neighborhood: Neighborhood) -> Self {
+        assert!(dims > 0, "a universe needs at least one axis");
+
+        DynLife {
+            dims,
+            cells: HashSet::new(),
+            decaying: HashMap::new(),
+            neighbors: neighborhood.offsets_dyn(dims),
+            rule,
+            generation: 0,
+        }
+    }
+
+    /// number of axes
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    /// number of cycles since the universe was created
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn check(&self, pos: &[i32]) {
+        assert!(
+            pos.len() == self.dims,
+            "expected {} coordinates, got {}",
+            self.dims,
+            pos.len()
+        );
+    }
+
+    /// return true if there is a live cell at the position
+    pub fn get(&self, pos: &[i32]) -> bool {
+        self.check(pos);
+        self.cells.contains(pos)
+    }
+
+    /// state of the cell at the position, 0 is dead, 1 is alive
+    /// and higher states are decaying cells of Generations rules
+    pub fn state(&self, pos: &[i32]) -> u8 {
+        self.check(pos);
+        if self.cells.contains(pos) {
+            1
+        } else {
+            self.decaying.get(pos).copied().unwrap_or(0)
+        }
+    }
+
+    /// create a live cell at the position
+    pub fn create(&mut self, pos: &[i32]) {
+        self.check(pos);
+        self.decaying.remove(pos);
+        self.cells.insert(pos.to_vec());
+    }
+
+    /// kill the cell at the position, decaying cells are cleared too
+    pub fn kill(&mut self, pos: &[i32]) {
+        self.check(pos);
+        self.decaying.remove(pos);
+        self.cells.remove(pos);
+    }
+
+    /// perform a life cycle
+    /// each live cell adds one to the cells it is a neighbor of, which for
+    /// offset d is the cell at c - d
+    /// panics if the pattern reaches the edge of the coordinate range
+    pub fn cycle(&mut self) {
+        if let Err(e) = self.try_cycle() {
+            panic!("{}", e);
+        }
+    }
+
+    /// perform a life cycle, or leave the universe unchanged and return
+    /// an error if the pattern reaches the edge of the coordinate range
+    pub fn try_cycle(&mut self) -> Result<(), OutOfRange> {
+        let reach = self.neighbors.iter().flatten().map(|x| x.abs()).max();
+        let inside = i32::MIN + reach.unwrap_or(0)..=i32::MAX - reach.unwrap_or(0);
+        if let Some(c) = self
+            .cells
+            .iter()
+            .find(|c| !c.iter().all(|x| inside.contains(x)))
+        {
+            return Err(OutOfRange::new(c));
+        }
+
+        let mut counts: HashMap<DynVector, usize> = HashMap::new();
+
+        for c in self.cells.iter() {
+            for d in self.neighbors.iter() {
+                let pos = c.iter().zip(d).map(|(x, y)| x - y).collect();
+                *counts.entry(pos).or_insert(0) += 1;
+            }
+        }
+
+        let mut next: HashSet<_> = counts
+            .iter()
+            .filter(|(pos, &n)| {
+                if self.cells.contains(*pos) {
+                    self.rule.survives(n)
+                } else {
+                    // decaying cells can't be born
+                    self.rule.born(n) && !self.decaying.contains_key(*pos)
+                }
+            })
+            .map(|(pos, _)| pos.clone())
+            .collect();
+
+        if self.rule.survives(0) {
+            let lonely = self.cells.iter().filter(|c| !counts.contains_key(*c));
+            next.extend(lonely.cloned());
+        }
+
+        let died: Vec<_> = self.cells.difference(&next).cloned().collect();
+        self.decay(died);
+        self.cells = next;
+        self.generation += 1;
+
+        Ok(())
+    }
+
+    // advance the decaying cells and start decaying the live cells that
+    // died, two-state rules have nothing to do here
+    fn decay(&mut self, died: Vec<DynVector>) {
+        if self.rule.states <= 2 {
+            return;
+        }
+
+        let states = self.rule.states;
+        let mut decaying: HashMap<_, _> = self
+            .decaying
+            .drain()
+            .filter(|&(_, s)| s + 1 < states)
+            .map(|(pos, s)| (pos, s + 1))
+            .collect();
+        decaying.extend(died.into_iter().map(|c| (c, 2)));
+
+        self.decaying = decaying;
+    }
+
+    /// perform k life cycles
+    pub fn step_n(&mut self, k: u64) {
+        for _ in 0..k {
+            self.cycle();
+        }
+    }
+
+    /// number of live cells
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// the positions of all live cells, in no particular order
+    pub fn cells(&self) -> impl Iterator<Item = &[i32]> + '_ {
+        self.cells.iter().map(|c| &c[..])
+    }
+}
+
+/// a universe with the number of dimensions chosen at runtime, using the
+/// const-generic Life for 2 to 7 dimensions and DynLife otherwise
+///
+/// unstable, this can change in any release
+pub enum AnyLife {
+    /// two dimensions
+    D2(Life<2>),
+    /// three dimensions
+    D3(Life<3>),
+    /// four dimensions
+    D4(Life<4>),
+    /// five dimensions
+    D5(Life<5>),
+    /// six dimensions
+    D6(Life<6>),
+    /// seven dimensions
+    D7(Life<7>),
+    /// any other number of dimensions
+    Dyn(DynLife),
+}
+
+// the position as an array for the const-generic universes
+fn fixed<const N: usize>(pos: &[i32]) -> Vector<N> {
+    pos.try_into()
+        .unwrap_or_else(|_| panic!("expected {} coordinates, got {}", N, pos.len()))
+}
+
+// the same expression for every const-generic variant, and another one
+// for DynLife
+macro_rules! dispatch {
+    ($any:expr, $life:ident => $fixed:expr, $dynamic:ident => $other:expr) => {
+        match $any {
+            AnyLife::D2($life) => $fixed,
+            AnyLife::D3($life) => $fixed,
+            AnyLife::D4($life) => $fixed,
+            AnyLife::D5($life) => $fixed,
+            AnyLife::D6($life) => $fixed,
+            AnyLife::D7($life) => $fixed,
+            AnyLife::Dyn($dynamic) => $other,
+        }
+    };
+}
+
+impl AnyLife {
+    /// an empty universe with the given number of axes, playing the
+    /// original game
+    pub fn new(dims: usize) -> Self {
+        Self::with_rule(dims, Rule::default())
+    }
+
+    /// an empty universe with another rule on the Moore neighborhood
+    pub fn with_rule(dims: usize, rule: Rule) -> Self {
+        Self::with_neighborhood(dims, rule, Neighborhood::default())
+    }
+
+    /// an empty universe with another rule and neighborhood, backed by the
+    /// variant for its number of axes
+    pub fn with_neighborhood(dims: usize, rule: Rule, neighborhood: Neighborhood) -> Self {
+        match dims {
+            2 => AnyLife::D2(Life::with_neighborhood(rule, neighborhood)),
+            3 => AnyLife::D3(Life::with_neighborhood(rule, neighborhood)),
+            4 => AnyLife::D4(Life::with_neighborhood(rule, neighborhood)),
+            5 => AnyLife::D5(Life::with_neighborhood(rule, neighborhood)),
+            6 => AnyLife::D6(Life::with_neighborhood(rule, neighborhood)),
+            7 => AnyLife::D7(Life::with_neighborhood(rule, neighborhood)),
+            _ => AnyLife::Dyn(DynLife::with_neighborhood(dims, rule, neighborhood)),
+        }
+    }
+
+    /// number of axes
+    pub fn dims(&self) -> usize {
+        match self {
+            AnyLife::D2(_) => 2,
+            AnyLife::D3(_) => 3,
+            AnyLife::D4(_) => 4,
+            AnyLife::D5(_) => 5,
+            AnyLife::D6(_) => 6,
+            AnyLife::D7(_) => 7,
+            AnyLife::Dyn(life) => life.dims(),
+        }
+    }
+
+    /// return true if there is a live cell at the position
+    pub fn get(&self, pos: &[i32]) -> bool {
+        dispatch!(self, life => life.get(&fixed(pos)), life => life.get(pos))
+    }
+
+    /// state of the cell at the position, 0 is dead, 1 is alive
+    /// and higher states are decaying cells of Generations rules
+    pub fn state(&self, pos: &[i32]) -> u8 {
+        dispatch!(self, life => life.state(&fixed(pos)), life => life.state(pos))
+    }
+
+    /// make the cell at the position alive or dead
+    pub fn set(&mut self, pos: &[i32], alive: bool) {
+        dispatch!(
+            self,
+            life => Universe::set(life, fixed(pos), alive),
+            life => if alive { life.create(pos) } else { life.kill(pos) }
+        )
+    }
+
+    /// create a live cell at the position
+    pub fn create(&mut self, pos: &[i32]) {
+        self.set(pos, true);
+    }
+
+    /// kill the cell at the position
+    pub fn kill(&mut self, pos: &[i32]) {
+        self.set(pos, false);
+    }
+
+    /// perform a life cycle
+    /// panics if the pattern reaches the edge of the coordinate range
+    pub fn cycle(&mut self) {
+        dispatch!(self, life => life.cycle(), life => life.cycle())
+    }
+
+    /// perform a life cycle, or leave the universe unchanged and return
+    /// an error if the pattern reaches the edge of the coordinate range
+    pub fn try_cycle(&mut self) -> Result<(), OutOfRange> {
+        dispatch!(self, life => life.try_cycle(), life => life.try_cycle())
+    }
+
+    /// number of cycles since the universe was created
+    pub fn generation(&self) -> u64 {
+        dispatch!(self, life => life.generation(), life => life.generation())
+    }
+
+    /// perform k life cycles
+    pub fn step_n(&mut self, k: u64) {
+        dispatch!(self, life => life.step_n(k), life => life.step_n(k))
+    }
+
+    /// number of live cells
+    pub fn population(&self) -> usize {
+        dispatch!(self, life => life.cells.len(), life => life.population())
+    }
+
+    /// the positions of all live cells, in no particular order
+    pub fn cells(&self) -> Box<dyn Iterator<Item = DynVector> + '_> {
+        dispatch!(
+            self,
+            life => Box::new(life.cells.iter().map(|c| c.to_vec())),
+            life => Box::new(life.cells().map(|c| c.to_vec()))
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use crate::testing::Backend;
+
+    impl<const N: usize> Backend<N> for DynLife {
+        fn create(&mut self, pos: Vector<N>) {
+            DynLife::create(self, &pos);
+        }
+
+        fn cycle(&mut self) {
+            DynLife::cycle(self);
+        }
+
+        fn live(&self) -> HashSet<Vector<N>> {
+            self.cells().map(fixed).collect()
+        }
+    }
+
+    impl<const N: usize> Backend<N> for AnyLife {
+        fn create(&mut self, pos: Vector<N>) {
+            AnyLife::create(self, &pos);
+        }
+
+        fn cycle(&mut self) {
+            AnyLife::cycle(self);
+        }
+
+        fn live(&self) -> HashSet<Vector<N>> {
+            self.cells().map(|c| fixed(&c)).collect()
+        }
+    }
+
+    fn compare<const N: usize>(rule: Rule, seed: &[Vector<N>], generations: usize) {
+        testing::compare(
+            &mut DynLife::with_rule(N, rule.clone()),
+            rule.clone(),
+            seed,
+            generations,
+        );
+
+        let mut any = AnyLife::with_rule(N, rule.clone());
+        testing::compare(&mut any, rule, seed, generations);
+        assert_eq!(any.population(), Backend::<N>::live(&any).len());
+        assert_eq!(any.generation(), generations as u64);
+    }
+
+    #[test]
+    fn matches_life() {
+        // the R-pentomino
+        let r = [[1, 0], [2, 0], [0, 1], [1, 1], [1, 2]];
+        compare::<2>(Rule::default(), &r, 30);
+
+        let seed = [[0, 0, 0], [1, 0, 0], [0, 1, 0], [0, 0, 1], [2, 1, 0]];
+        compare::<3>("B5/S45".parse().unwrap(), &seed, 6);
+
+        let seed = [[0, 1, 0, 0, 0], [0, 0, 0, 0, 0], [0, -1, 0, 0, 0]];
+        compare::<5>(Rule::default(), &seed, 3);
+
+        // Brian's Brain, where dying cells block births for a generation
+        let seed = [[0, 0, 0], [1, 0, 0], [0, 1, 1], [2, 2, 0], [1, 2, 2]];
+        compare::<3>("B2/S/C3".parse().unwrap(), &seed, 8);
+    }
+
+    #[test]
+    fn generations() {
+        // every count gives birth, cells decay through one extra state
+        let rule = Rule::generations(1..=8, [], 3);
+        let mut life = DynLife::with_rule(2, rule);
+        life.create(&[0, 0]);
+
+        life.cycle();
+        assert_eq!(life.population(), 8);
+        assert_eq!(life.state(&[0, 0]), 2);
+
+        // the center has 8 live neighbors but it is still decaying
+        life.cycle();
+        assert_eq!(life.state(&[0, 0]), 0);
+        assert_eq!(life.state(&[1, 1]), 2);
+        assert_eq!(life.state(&[2, 2]), 1);
+        assert_eq!(life.population(), 16);
+    }
+
+    #[test]
+    fn dispatch() {
+        assert!(matches!(AnyLife::new(2), AnyLife::D2(_)));
+        assert!(matches!(AnyLife::new(4), AnyLife::D4(_)));
+        assert!(matches!(AnyLife::new(7), AnyLife::D7(_)));
+
+        let mut any = AnyLife::new(8);
+        assert!(matches!(any, AnyLife::Dyn(_)));
+        assert_eq!(any.dims(), 8);
+
+        any.create(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(any.get(&[1, 2, 3, 4, 5, 6, 7, 8]));
+        any.kill(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(any.population(), 0);
+    }
+
+    #[test]
+    fn coordinate_range() {
+        // a blinker across the top of the range stops with an error
+        // instead of overflowing
+        for mut any in [AnyLife::new(3), AnyLife::new(8)] {
+            let dims = any.dims();
+            for x in -1..=1 {
+                let mut pos = vec![i32::MAX - 1; dims];
+                pos[0] = x;
+                any.create(&pos);
+            }
+            assert!(any.try_cycle().is_ok());
+
+            // now it reaches the edge along axis 1
+            let before: HashSet<_> = any.cells().collect();
+            let err = any.try_cycle().unwrap_err();
+            assert!(err.position()[1] >= i32::MAX as i64 - 2);
+            assert_eq!(any.cells().collect::<HashSet<_>>(), before);
+        }
+    }
+}
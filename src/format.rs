@@ -0,0 +1,246 @@
+//! reading and writing pattern files
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+use crate::rule::Rule;
+use crate::universe::Universe;
+use crate::Vector;
+
+pub mod aoc;
+pub mod life105;
+pub mod life106;
+pub mod macrocell;
+pub mod plaintext;
+pub mod rle;
+
+/// the pattern file formats that can be read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// the run length encoded .rle format
+    Rle,
+    /// the .cells format
+    Plaintext,
+    /// Life 1.05, blocks of rows of cells
+    Life105,
+    /// Life 1.06, one cell per line
+    Life106,
+    /// Golly's .mc format, read through hashlife
+    Macrocell,
+}
+
+impl Format {
+    /// guess the format of a pattern file from its contents, None if it
+    /// doesn't look like any of them
+    pub fn sniff(text: &str) -> Option<Format> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let first = lines.next()?;
+
+        if first.starts_with(life105::HEADER) {
+            return Some(Format::Life105);
+        }
+        if first.starts_with(life106::HEADER) {
+            return Some(Format::Life106);
+        }
+        if first.starts_with(macrocell::HEADER) {
+            return Some(Format::Macrocell);
+        }
+        if first.starts_with('!') || first.chars().all(|c| matches!(c, '.' | 'O' | '*')) {
+            return Some(Format::Plaintext);
+        }
+
+        // RLE can start with # lines, then comes the header
+        let header = std::iter::once(first)
+            .chain(lines)
+            .find(|l| !l.starts_with('#'))?;
+        match header.split_once('=') {
+            Some((key, _)) if key.trim() == "x" => Some(Format::Rle),
+            _ => None,
+        }
+    }
+
+    /// read a pattern in this format
+    pub fn read<const N: usize>(self, text: &str) -> Result<Pattern<N>, ParsePatternError> {
+        match self {
+            Format::Rle => rle::read(text),
+            Format::Plaintext => plaintext::read(text),
+            Format::Life105 => life105::read(text),
+            Format::Life106 => life106::read(text),
+            Format::Macrocell => macrocell::read_pattern(text),
+        }
+    }
+}
+
+/// read a pattern file in any of the formats, guessing which one from its
+/// contents
+pub fn read<const N: usize>(text: &str) -> Result<Pattern<N>, ParsePatternError> {
+    match Format::sniff(text) {
+        Some(format) => format.read(text),
+        None => Err(ParsePatternError::new(1, 1, "unknown pattern format")),
+    }
+}
+
+// a cell of a two dimensional pattern in N dimensions, the other axes are
+// 0, None if it doesn't fit in fewer dimensions
+pub(crate) fn planar<const N: usize>(x: i32, y: i32) -> Option<Vector<N>> {
+    let mut pos = [0; N];
+    for (axis, v) in [x, y].into_iter().enumerate() {
+        match pos.get_mut(axis) {
+            Some(p) => *p = v,
+            None if v == 0 => {}
+            None => return None,
+        }
+    }
+    Some(pos)
+}
+
+// the rows of cells between min and max, `.` for dead cells and `live` for
+// live ones, without the dead cells at the end of a row
+pub(crate) fn write_rows(
+    out: &mut String,
+    cells: &HashSet<Vector<2>>,
+    (min, max): (Vector<2>, Vector<2>),
+    live: char,
+) {
+    let mut rows: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+    for &[x, y] in cells.iter() {
+        rows.entry(y).or_default().push(x);
+    }
+
+    for y in min[1]..=max[1] {
+        let mut row = String::new();
+        if let Some(xs) = rows.get_mut(&y) {
+            xs.sort_unstable();
+            let mut x = min[0];
+            for &cx in xs.iter() {
+                row.extend((x..cx).map(|_| '.'));
+                row.push(live);
+                x = cx + 1;
+            }
+        }
+        out.push_str(if row.is_empty() { "." } else { &row });
+        out.push('\n');
+    }
+}
+
+/// the contents of a pattern file
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pattern<const N: usize> {
+    /// the live cells
+    pub cells: HashSet<Vector<N>>,
+    /// the rule given by the file, None if it doesn't have one
+    pub rule: Option<Rule>,
+    /// the name given by the file, None if it doesn't have one
+    pub name: Option<String>,
+    /// the comment lines, without the comment markers
+    pub comments: Vec<String>,
+}
+
+impl<const N: usize> Pattern<N> {
+    /// a pattern with the cells and nothing else
+    pub fn new(cells: HashSet<Vector<N>>) -> Self {
+        Pattern {
+            cells,
+            ..Pattern::default()
+        }
+    }
+
+    /// the live cells of a universe
+    pub fn from_universe<U: Universe<N> + ?Sized>(universe: &U) -> Self {
+        Self::new(universe.cells().collect())
+    }
+
+    /// create the cells of the pattern in a universe
+    pub fn create_in<U: Universe<N> + ?Sized>(&self, universe: &mut U) {
+        for &c in self.cells.iter() {
+            universe.create(c);
+        }
+    }
+
+    /// the smallest and largest coordinates along each axis, None if the
+    /// pattern is empty
+    pub(crate) fn bounds(&self) -> Option<(Vector<N>, Vector<N>)> {
+        self.cells.iter().fold(None, |bounds, c| {
+            let (mut min, mut max) = bounds.unwrap_or((*c, *c));
+            for (a, &x) in c.iter().enumerate() {
+                min[a] = min[a].min(x);
+                max[a] = max[a].max(x);
+            }
+            Some((min, max))
+        })
+    }
+}
+
+/// a pattern file that couldn't be read, lines and columns count from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsePatternError {
+    line: usize,
+    column: usize,
+    reason: String,
+}
+
+impl ParsePatternError {
+    pub(crate) fn new(line: usize, column: usize, reason: impl Into<String>) -> Self {
+        ParsePatternError {
+            line,
+            column,
+            reason: reason.into(),
+        }
+    }
+
+    /// the line of the error
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// the column of the error
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for ParsePatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.reason)
+    }
+}
+
+impl Error for ParsePatternError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Life;
+
+    #[test]
+    fn sniff() {
+        let glider = HashSet::from([[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]]);
+        let files = [
+            ("#C a glider\nx = 3, y = 3\nbo$2bo$3o!\n", Format::Rle),
+            ("!Name: Glider\n.O.\n..O\nOOO\n", Format::Plaintext),
+            (".O.\n..O\nOOO\n", Format::Plaintext),
+            ("#Life 1.05\n#P 0 0\n.*.\n..*\n***\n", Format::Life105),
+            ("#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n", Format::Life106),
+            (
+                "[M2] (golly 4.2)\n.*$..*$***$\n4 0 0 0 1\n",
+                Format::Macrocell,
+            ),
+        ];
+
+        for (text, format) in files {
+            assert_eq!(Format::sniff(text), Some(format));
+            let pattern = read::<2>(text).unwrap();
+            assert_eq!(pattern.cells, glider);
+
+            let mut life = Life::<3>::new();
+            read::<3>(text).unwrap().create_in(&mut life);
+            assert!(life.get(&[1, 0, 0]));
+        }
+
+        assert_eq!(Format::sniff(""), None);
+        assert_eq!(Format::sniff("#C only comments\n"), None);
+        assert_eq!(read::<2>("hello\n").unwrap_err().line(), 1);
+    }
+}
@@ -0,0 +1,145 @@
+//! grids of `#` and `.` lines like the puzzle inputs of Advent of Code,
+//! placed into any number of dimensions
+//!
+//! unstable, this can change in any release
+
+use crate::dynamic::DynVector;
+use crate::format::ParsePatternError;
+use crate::format::Pattern;
+use crate::universe::Universe;
+
+/// where a grid goes in a universe with more dimensions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    /// the position of the top left character, the axes that aren't
+    /// given are 0
+    pub offset: DynVector,
+    /// the axes along the columns and the rows of the grid
+    pub axes: [usize; 2],
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement {
+            offset: Vec::new(),
+            axes: [0, 1],
+        }
+    }
+}
+
+/// the live cells of a grid of `#` and `.` lines, like the puzzle inputs
+/// of Advent of Code, placed in a universe with dims dimensions
+/// by default the columns go along x and the rows along y from the
+/// origin, with all the other axes 0
+pub fn cells(
+    text: &str,
+    dims: usize,
+    placement: &Placement,
+) -> Result<Vec<DynVector>, ParsePatternError> {
+    let [ax, ay] = placement.axes;
+    assert!(
+        ax < dims && ay < dims && ax != ay,
+        "invalid axes {:?} in {} dimensions",
+        placement.axes,
+        dims
+    );
+    assert!(
+        placement.offset.len() <= dims,
+        "expected at most {} coordinates, got {}",
+        dims,
+        placement.offset.len()
+    );
+
+    let mut origin = vec![0; dims];
+    origin[..placement.offset.len()].copy_from_slice(&placement.offset);
+
+    let mut cells = Vec::new();
+    for (y, line) in text.lines().enumerate() {
+        for (x, c) in line.trim_end().chars().enumerate() {
+            match c {
+                '.' => {}
+                '#' => {
+                    let too_large = || ParsePatternError::new(y + 1, x + 1, "pattern too large");
+                    let mut pos = origin.clone();
+                    pos[ax] = i32::try_from(x)
+                        .ok()
+                        .and_then(|x| pos[ax].checked_add(x))
+                        .ok_or_else(too_large)?;
+                    pos[ay] = i32::try_from(y)
+                        .ok()
+                        .and_then(|y| pos[ay].checked_add(y))
+                        .ok_or_else(too_large)?;
+                    cells.push(pos);
+                }
+                c => {
+                    let reason = format!("unexpected character `{}`", c);
+                    return Err(ParsePatternError::new(y + 1, x + 1, reason));
+                }
+            }
+        }
+    }
+
+    Ok(cells)
+}
+
+/// read a grid of `#` and `.` lines as a pattern in N dimensions
+pub fn read<const N: usize>(
+    text: &str,
+    placement: &Placement,
+) -> Result<Pattern<N>, ParsePatternError> {
+    let cells = cells(text, N, placement)?;
+    Ok(Pattern::new(
+        cells.iter().map(|c| c[..].try_into().unwrap()).collect(),
+    ))
+}
+
+/// create the live cells of a grid of `#` and `.` lines in a universe
+pub fn load<const N: usize, U: Universe<N> + ?Sized>(
+    text: &str,
+    placement: &Placement,
+    universe: &mut U,
+) -> Result<(), ParsePatternError> {
+    read(text, placement)?.create_in(universe);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Life;
+    use std::collections::HashSet;
+
+    // the example of Conway Cubes, day 17 of Advent of Code 2020
+    const CUBES: &str = ".#.\n..#\n###\n";
+
+    #[test]
+    fn conway_cubes() {
+        let mut life = Life::<3>::new();
+        load(CUBES, &Placement::default(), &mut life).unwrap();
+        assert!(life.get(&[1, 0, 0]));
+        life.step_n(6);
+        assert_eq!(life.population(), 112);
+
+        let mut life = Life::<4>::new();
+        load(CUBES, &Placement::default(), &mut life).unwrap();
+        life.step_n(6);
+        assert_eq!(life.population(), 848);
+    }
+
+    #[test]
+    fn placement() {
+        let placement = Placement {
+            offset: vec![10, 0, -1],
+            axes: [2, 0],
+        };
+        let pattern = read::<4>("#.\r\n.#\n", &placement).unwrap();
+        let expected = HashSet::from([[10, 0, -1, 0], [11, 0, 0, 0]]);
+        assert_eq!(pattern.cells, expected);
+
+        let rod = cells("#\n#\n", 5, &Placement::default()).unwrap();
+        assert_eq!(rod, [vec![0; 5], vec![0, 1, 0, 0, 0]]);
+
+        let err = read::<3>("..#\n.o.\n", &Placement::default()).unwrap_err();
+        assert_eq!((err.line(), err.column()), (2, 2));
+    }
+}
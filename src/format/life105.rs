@@ -0,0 +1,175 @@
+//! the Life 1.05 format, blocks of rows of cells
+
+use std::fmt::Write;
+
+use crate::format::planar;
+use crate::format::write_rows;
+use crate::format::ParsePatternError;
+use crate::format::Pattern;
+use crate::rule::Rule;
+
+/// the first line of a Life 1.05 file
+pub const HEADER: &str = "#Life 1.05";
+
+/// read a pattern in the Life 1.05 format
+///
+/// after the `#Life 1.05` header, `#D` lines are comments, `#N` selects
+/// the normal rule and `#R 23/36` another one, survival first, the
+/// `#R B36/S23` form is accepted too, then come blocks of cells
+/// each starting with `#P x y`, the position of its top left corner,
+/// followed by rows of cells from the top, `.` is a dead cell and `*` a
+/// live one
+/// the pattern is read into the z = 0 plane in more dimensions
+pub fn read<const N: usize>(text: &str) -> Result<Pattern<N>, ParsePatternError> {
+    let mut pattern = Pattern::default();
+    let mut lines = text.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+    match lines.find(|(_, l)| !l.trim().is_empty()) {
+        Some((_, line)) if line.trim_end() == HEADER => {}
+        Some((n, _)) => return Err(ParsePatternError::new(n, 1, "missing `#Life 1.05` header")),
+        None => return Err(ParsePatternError::new(1, 1, "missing `#Life 1.05` header")),
+    }
+
+    // the top left corner of the current block and the row in it
+    let mut origin = [0, 0];
+    let mut y = 0;
+
+    for (n, line) in lines {
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut chars = comment.chars();
+            let kind = chars.next();
+            let text = chars.as_str().trim();
+
+            match kind {
+                Some('D') => {
+                    let text = chars.as_str();
+                    let text = text.strip_prefix(' ').unwrap_or(text);
+                    pattern.comments.push(text.trim_end().to_string());
+                }
+                Some('N') => pattern.rule = Some(Rule::default()),
+                Some('R') => {
+                    let rule = text.parse().map_err(|e| {
+                        ParsePatternError::new(n, 3, format!("invalid rule: {}", e))
+                    })?;
+                    pattern.rule = Some(rule);
+                }
+                Some('P') => {
+                    let coords: Result<Vec<i32>, _> =
+                        text.split_whitespace().map(|t| t.parse()).collect();
+                    match coords.as_deref() {
+                        Ok(&[px, py]) => origin = [px, py],
+                        _ => return Err(ParsePatternError::new(n, 1, "invalid position")),
+                    }
+                    y = 0;
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                '*' | 'O' => {
+                    let too_large = || ParsePatternError::new(n, x + 1, "pattern too large");
+                    let cx = origin[0].checked_add(x as i32).ok_or_else(too_large)?;
+                    let cy = origin[1].checked_add(y).ok_or_else(too_large)?;
+                    let pos = planar(cx, cy).ok_or_else(|| {
+                        let reason = format!("the pattern has more than {} dimensions", N);
+                        ParsePatternError::new(n, x + 1, reason)
+                    })?;
+                    pattern.cells.insert(pos);
+                }
+                c if c.is_whitespace() => {}
+                c => {
+                    let reason = format!("unexpected character `{}`", c);
+                    return Err(ParsePatternError::new(n, x + 1, reason));
+                }
+            }
+        }
+        y += 1;
+    }
+
+    Ok(pattern)
+}
+
+/// write a pattern in the Life 1.05 format as a single block, the name
+/// isn't part of the format so it is lost
+/// the rule is written survival first like `#R 23/36`
+pub fn write(pattern: &Pattern<2>) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "{}", HEADER).unwrap();
+    for comment in pattern.comments.iter() {
+        writeln!(out, "#D {}", comment).unwrap();
+    }
+    match &pattern.rule {
+        Some(rule) if *rule == Rule::default() => writeln!(out, "#N").unwrap(),
+        Some(rule) => writeln!(out, "#R {}", rule.survival_first()).unwrap(),
+        None => {}
+    }
+
+    let Some((min, max)) = pattern.bounds() else {
+        return out;
+    };
+    writeln!(out, "#P {} {}", min[0], min[1]).unwrap();
+
+    write_rows(&mut out, &pattern.cells, (min, max), '*');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn blocks() {
+        let text = "#Life 1.05\n#D two gliders\n#R 23/36\n\
+                    #P -1 -1\n.*\n..*\n***\n#P 10 0\n*\n";
+        let pattern = read::<2>(text).unwrap();
+
+        assert_eq!(pattern.comments, ["two gliders"]);
+        assert_eq!(pattern.rule, Some("B36/S23".parse().unwrap()));
+        let prefixed = read::<2>("#Life 1.05\n#R B36/S23\n").unwrap();
+        assert_eq!(prefixed.rule, pattern.rule);
+        let cells = HashSet::from([[0, -1], [1, 0], [-1, 1], [0, 1], [1, 1], [10, 0]]);
+        assert_eq!(pattern.cells, cells);
+
+        assert_eq!(
+            read::<2>("#Life 1.05\n#N\n").unwrap().rule,
+            Some(Rule::default())
+        );
+
+        let err = read::<2>("x = 1, y = 1\no!\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (1, 1));
+        let err = read::<2>("#Life 1.05\n#P 1\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (2, 1));
+        let err = read::<2>("#Life 1.05\n#P 0 0\n.*.\n.x.\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (4, 2));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut pattern = Pattern::new(HashSet::from([[-4, 2], [1, 2], [-2, 5], [-1, 5]]));
+        pattern.rule = Some(Rule::default());
+        pattern.comments = vec!["a comment".to_string()];
+
+        let text = write(&pattern);
+        assert_eq!(
+            text,
+            "#Life 1.05\n#D a comment\n#N\n#P -4 2\n*....*\n.\n.\n..**\n"
+        );
+        assert_eq!(read::<2>(&text).unwrap(), pattern);
+
+        pattern.rule = Some("B36/S23".parse().unwrap());
+        let text = write(&pattern);
+        assert!(text.contains("\n#R 23/36\n"));
+        assert_eq!(read::<2>(&text).unwrap(), pattern);
+
+        pattern.rule = Some("B36/S23/C3".parse().unwrap());
+        let text = write(&pattern);
+        assert!(text.contains("\n#R 23/36/3\n"));
+        assert_eq!(read::<2>(&text).unwrap(), pattern);
+    }
+}
@@ -0,0 +1,130 @@
+//! the Life 1.06 format, one cell per line
+
+use std::fmt::Write;
+
+use crate::format::ParsePatternError;
+use crate::format::Pattern;
+use crate::Vector;
+
+/// the first line of a Life 1.06 file
+pub const HEADER: &str = "#Life 1.06";
+
+/// read a pattern in the Life 1.06 format, extended to N dimensions
+///
+/// after the `#Life 1.06` header each line gives the coordinates of a
+/// live cell, `x y` in two dimensions, `#D` lines are comments
+/// a line with fewer coordinates than N has the others set to 0, so a
+/// standard file is read into the z = 0 plane
+pub fn read<const N: usize>(text: &str) -> Result<Pattern<N>, ParsePatternError> {
+    let mut pattern = Pattern::default();
+    let mut lines = text.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+    match lines.find(|(_, l)| !l.trim().is_empty()) {
+        Some((_, line)) if line.trim_end() == HEADER => {}
+        Some((n, _)) => return Err(ParsePatternError::new(n, 1, "missing `#Life 1.06` header")),
+        None => return Err(ParsePatternError::new(1, 1, "missing `#Life 1.06` header")),
+    }
+
+    for (n, line) in lines {
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some(text) = comment.strip_prefix('D') {
+                let text = text.strip_prefix(' ').unwrap_or(text);
+                pattern.comments.push(text.trim_end().to_string());
+            }
+            continue;
+        }
+
+        let mut pos = [0; N];
+        let mut axis = 0;
+        let mut chars = line.char_indices().peekable();
+        while let Some(&(start, c)) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+                continue;
+            }
+
+            let mut end = line.len();
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_whitespace() {
+                    end = i;
+                    break;
+                }
+                chars.next();
+            }
+
+            let column = line[..start].chars().count() + 1;
+            if axis >= N {
+                let reason = format!("the pattern has more than {} dimensions", N);
+                return Err(ParsePatternError::new(n, column, reason));
+            }
+            let token = &line[start..end];
+            pos[axis] = token.parse().map_err(|_| {
+                ParsePatternError::new(n, column, format!("invalid coordinate `{}`", token))
+            })?;
+            axis += 1;
+        }
+
+        if axis > 0 {
+            pattern.cells.insert(pos);
+        }
+    }
+
+    Ok(pattern)
+}
+
+/// write a pattern in the Life 1.06 format with N coordinates per cell,
+/// the name and the rule aren't part of the format so they are lost
+pub fn write<const N: usize>(pattern: &Pattern<N>) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "{}", HEADER).unwrap();
+    for comment in pattern.comments.iter() {
+        writeln!(out, "#D {}", comment).unwrap();
+    }
+
+    // rows first, like the other formats
+    let mut cells: Vec<Vector<N>> = pattern.cells.iter().copied().collect();
+    cells.sort_unstable_by(|a, b| a.iter().rev().cmp(b.iter().rev()));
+
+    for c in cells {
+        let coords: Vec<String> = c.iter().map(|x| x.to_string()).collect();
+        writeln!(out, "{}", coords.join(" ")).unwrap();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn glider() {
+        let text = "#Life 1.06\n#D a glider\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+        let pattern = read::<2>(text).unwrap();
+
+        assert_eq!(pattern.comments, ["a glider"]);
+        let cells = HashSet::from([[0, -1], [1, 0], [-1, 1], [0, 1], [1, 1]]);
+        assert_eq!(pattern.cells, cells);
+
+        let pattern = read::<3>(text).unwrap();
+        assert!(pattern.cells.contains(&[0, -1, 0]));
+
+        let err = read::<2>("#Life 1.06\n0 0\n1  x\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (3, 4));
+        let err = read::<2>("#Life 1.06\n0 0 1\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (2, 5));
+        assert_eq!(read::<2>("#Life 1.05\n").unwrap_err().line(), 1);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut pattern = Pattern::new(HashSet::from([[3, -1, 2], [0, 0, 0], [-5, 0, 0]]));
+        pattern.comments = vec!["a comment".to_string()];
+
+        let text = write(&pattern);
+        assert_eq!(text, "#Life 1.06\n#D a comment\n-5 0 0\n0 0 0\n3 -1 2\n");
+        assert_eq!(read::<3>(&text).unwrap(), pattern);
+    }
+}
@@ -0,0 +1,349 @@
+//! Golly's macrocell format, a serialized quadtree read into hashlife
+//!
+//! unstable, this can change in any release
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::format::planar;
+use crate::format::ParsePatternError;
+use crate::format::Pattern;
+use crate::hashlife::HashLife;
+use crate::hashlife::Id;
+use crate::rule::Rule;
+
+/// the start of the first line of a macrocell file
+pub const HEADER: &str = "[M2]";
+
+// the leaves of the file are squares of 8x8 cells
+const LEAF_LEVEL: u8 = 3;
+const LEAF: usize = 1 << LEAF_LEVEL;
+
+// largest level of a node, so that the root can still be expanded in
+// the i64 coordinates of hashlife
+const MAX_LEVEL: u8 = 62;
+
+/// read a pattern in Golly's macrocell format straight into the tree of
+/// hashlife, so huge repetitive patterns stay compressed
+///
+/// after the `[M2]` header, `#R` gives the rule and `#G` the generation,
+/// then each line is a node, numbered from 1: a leaf is the rows of an
+/// 8x8 square from the top separated by `$`, `.` is a dead cell and `*`
+/// a live one, the other nodes are `k nw ne sw se` with k the level, a
+/// node of level k having 2^k cells on a side, and the numbers of its
+/// four children, 0 for an empty one
+/// the last node is the root, centered on the origin, y grows downward
+/// only two-state rules without B0 are supported, comments are skipped
+pub fn read(text: &str) -> Result<HashLife<2>, ParsePatternError> {
+    let mut life = HashLife::new();
+    let mut lines = text.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+    match lines.find(|(_, l)| !l.trim().is_empty()) {
+        Some((_, line)) if line.trim_start().starts_with(HEADER) => {}
+        Some((n, _)) => return Err(ParsePatternError::new(n, 1, "missing `[M2]` header")),
+        None => return Err(ParsePatternError::new(1, 1, "missing `[M2]` header")),
+    }
+
+    // the id in the tree of each node of the file
+    let mut ids: Vec<Id> = Vec::new();
+
+    for (n, line) in lines {
+        let column = |t: &str| {
+            line[..t.as_ptr() as usize - line.as_ptr() as usize]
+                .chars()
+                .count()
+                + 1
+        };
+        let trimmed = line.trim();
+
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            let mut chars = comment.chars();
+            let kind = chars.next();
+            let value = chars.as_str().trim();
+
+            match kind {
+                Some('R') => {
+                    let rule: Rule = value.parse().map_err(|e| {
+                        ParsePatternError::new(n, column(value), format!("invalid rule: {}", e))
+                    })?;
+                    if rule.states != 2 || rule.born(0) {
+                        let reason = format!("hashlife doesn't support {}", rule);
+                        return Err(ParsePatternError::new(n, column(value), reason));
+                    }
+                    // nothing has been advanced yet, so no result depends
+                    // on the old rule
+                    life.rule = rule;
+                }
+                Some('G') => {
+                    life.generation = value.parse().map_err(|_| {
+                        let reason = format!("invalid generation `{}`", value);
+                        ParsePatternError::new(n, column(value), reason)
+                    })?;
+                }
+                // comments, the name and anything else
+                _ => {}
+            }
+            continue;
+        }
+
+        let id = match trimmed.chars().next() {
+            None => continue,
+            Some('.' | '*' | '$') => read_leaf(&mut life, line, n)?,
+            Some(_) => {
+                let tokens: Vec<&str> = trimmed.split_whitespace().collect();
+                if tokens.len() != 5 {
+                    let reason = "expected `level nw ne sw se`";
+                    return Err(ParsePatternError::new(n, column(trimmed), reason));
+                }
+
+                let level = match tokens[0].parse::<u8>() {
+                    Ok(k) if (LEAF_LEVEL + 1..=MAX_LEVEL).contains(&k) => k,
+                    _ => {
+                        let reason = format!("invalid level `{}`", tokens[0]);
+                        return Err(ParsePatternError::new(n, column(tokens[0]), reason));
+                    }
+                };
+
+                let mut children = Vec::with_capacity(4);
+                for t in &tokens[1..] {
+                    let child = match t.parse::<usize>() {
+                        Ok(0) => Some(life.empty(level - 1)),
+                        Ok(i) => ids.get(i - 1).copied(),
+                        Err(_) => None,
+                    };
+                    match child {
+                        Some(child) if life.level(child) == level - 1 => children.push(child),
+                        _ => {
+                            let reason = format!("invalid node `{}` at level {}", t, level - 1);
+                            return Err(ParsePatternError::new(n, column(t), reason));
+                        }
+                    }
+                }
+
+                life.join(children)
+            }
+        };
+        ids.push(id);
+    }
+
+    if let Some(&root) = ids.last() {
+        life.root = root;
+    }
+
+    Ok(life)
+}
+
+// a leaf line, the rows of an 8x8 square from the top
+fn read_leaf(life: &mut HashLife<2>, line: &str, n: usize) -> Result<Id, ParsePatternError> {
+    let mut cells = [[false; LEAF]; LEAF];
+    let (mut x, mut y) = (0, 0);
+
+    for (i, c) in line.chars().enumerate() {
+        match c {
+            '.' => x += 1,
+            '*' => {
+                if x >= LEAF || y >= LEAF {
+                    return Err(ParsePatternError::new(n, i + 1, "leaf larger than 8x8"));
+                }
+                cells[y][x] = true;
+                x += 1;
+            }
+            '$' => {
+                x = 0;
+                y += 1;
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let reason = format!("unexpected character `{}`", c);
+                return Err(ParsePatternError::new(n, i + 1, reason));
+            }
+        }
+    }
+
+    Ok(build_leaf(life, &cells, [0, 0], LEAF_LEVEL))
+}
+
+// the node of the square of cells with its top left corner at pos
+fn build_leaf(
+    life: &mut HashLife<2>,
+    cells: &[[bool; LEAF]; LEAF],
+    pos: [usize; 2],
+    level: u8,
+) -> Id {
+    let [x, y] = pos;
+    if level == 0 {
+        return cells[y][x] as Id;
+    }
+
+    let half = 1 << (level - 1);
+    let children = (0..4)
+        .map(|c| {
+            build_leaf(
+                life,
+                cells,
+                [x + half * (c & 1), y + half * (c >> 1)],
+                level - 1,
+            )
+        })
+        .collect();
+    life.join(children)
+}
+
+/// read a macrocell file as a pattern, expanding the tree into its live
+/// cells, so this is only for patterns that fit in memory
+/// the pattern is read into the z = 0 plane in more dimensions
+pub fn read_pattern<const N: usize>(text: &str) -> Result<Pattern<N>, ParsePatternError> {
+    let life = read(text)?;
+    let cells = life
+        .try_to_cells::<i32>()
+        .map_err(|_| ParsePatternError::new(1, 1, "pattern too large"))?;
+
+    let mut pattern = Pattern::default();
+    for [x, y] in cells {
+        let pos = planar(x, y).ok_or_else(|| {
+            let reason = format!("the pattern has more than {} dimensions", N);
+            ParsePatternError::new(1, 1, reason)
+        })?;
+        pattern.cells.insert(pos);
+    }
+    pattern.rule = Some(life.rule.clone());
+
+    Ok(pattern)
+}
+
+/// write the tree of hashlife in the macrocell format, each distinct
+/// node once, so the file stays as small as the tree
+pub fn write(life: &HashLife<2>) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "{} (life {})", HEADER, env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(out, "#R {}", life.rule).unwrap();
+    if life.generation > 0 {
+        writeln!(out, "#G {}", life.generation).unwrap();
+    }
+
+    write_node(life, life.root, &mut HashMap::new(), &mut out);
+    out
+}
+
+// write the node after its children, return its number in the file
+fn write_node(
+    life: &HashLife<2>,
+    id: Id,
+    numbers: &mut HashMap<Id, usize>,
+    out: &mut String,
+) -> usize {
+    let node = &life.nodes[id as usize];
+    if node.population == 0 {
+        return 0;
+    }
+    if let Some(&k) = numbers.get(&id) {
+        return k;
+    }
+
+    if node.level == LEAF_LEVEL {
+        let mut cells = [[false; LEAF]; LEAF];
+        leaf_cells(life, id, [0, 0], &mut cells);
+
+        let rows = cells.iter().rposition(|r| r.contains(&true)).unwrap_or(0);
+        for row in cells[..=rows].iter() {
+            let len = row.iter().rposition(|&c| c).map_or(0, |x| x + 1);
+            out.extend(row[..len].iter().map(|&c| if c { '*' } else { '.' }));
+            out.push('$');
+        }
+        out.push('\n');
+    } else {
+        let children: Vec<usize> = node
+            .children
+            .iter()
+            .map(|&c| write_node(life, c, numbers, out))
+            .collect();
+        let [nw, ne, sw, se] = children[..] else {
+            unreachable!("a node of a quadtree has four children")
+        };
+        writeln!(out, "{} {} {} {} {}", node.level, nw, ne, sw, se).unwrap();
+    }
+
+    let k = numbers.len() + 1;
+    numbers.insert(id, k);
+    k
+}
+
+// the live cells of a node inside a leaf, pos is its top left corner
+fn leaf_cells(life: &HashLife<2>, id: Id, pos: [usize; 2], cells: &mut [[bool; LEAF]; LEAF]) {
+    let node = &life.nodes[id as usize];
+    let [x, y] = pos;
+    if node.level == 0 {
+        cells[y][x] = id == 1;
+        return;
+    }
+
+    let half = 1 << (node.level - 1);
+    for (c, &child) in node.children.iter().enumerate() {
+        leaf_cells(
+            life,
+            child,
+            [x + half * (c & 1), y + half * (c >> 1)],
+            cells,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    // a glider in the bottom right quarter of a 16x16 root
+    const GLIDER: &str = "[M2] (golly 4.2)\n#R B3/S23\n.*$..*$***$\n4 0 0 0 1\n";
+
+    #[test]
+    fn glider() {
+        let life = read(GLIDER).unwrap();
+        let cells = HashSet::from([[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]]);
+        assert_eq!(life.to_cells::<i32>(), cells);
+        assert_eq!(life.generation(), 0);
+
+        let text = write(&life);
+        assert!(text.ends_with("#R B3/S23\n.*$..*$***$\n4 0 0 0 1\n"));
+
+        let pattern = read_pattern::<3>(GLIDER).unwrap();
+        assert!(pattern.cells.contains(&[2, 2, 0]));
+        assert_eq!(pattern.rule, Some(Rule::default()));
+    }
+
+    // the line and column of the error reading the text
+    fn error(text: &str) -> (usize, usize) {
+        match read(text) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => (e.line(), e.column()),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("x = 3, y = 3\n"), (1, 1));
+        assert_eq!(error("[M2]\n.*$\n5 0 0 0 1\n"), (3, 9));
+        assert_eq!(error("[M2]\n.*$\n4 0 0 2 1\n"), (3, 7));
+        assert_eq!(error("[M2]\n.........*$\n"), (2, 10));
+        assert_eq!(error("[M2]\n#R B0/S8\n"), (2, 4));
+    }
+
+    #[test]
+    fn compressed() {
+        // a glider far away after 2^40 generations, far more than the
+        // cells of the tree could hold when expanded
+        let mut life = read(GLIDER).unwrap();
+        life.step_pow2(40);
+
+        let text = write(&life);
+        assert!(text.lines().count() < 100);
+
+        let copy = read(&text).unwrap();
+        assert_eq!(copy.generation(), 1 << 40);
+        assert_eq!(copy.population(), 5);
+        let d = 1i64 << 38;
+        assert!(copy.get(&[1 + d, d]));
+        assert_eq!(copy.to_cells::<i64>(), life.to_cells::<i64>());
+    }
+}
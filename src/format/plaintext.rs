@@ -0,0 +1,113 @@
+//! the plaintext .cells format, rows of `.` and `O`
+
+use std::fmt::Write;
+
+use crate::format::planar;
+use crate::format::write_rows;
+use crate::format::ParsePatternError;
+use crate::format::Pattern;
+
+/// read a pattern in the plaintext format of .cells files
+///
+/// lines starting with `!` are comments, `!Name: ` gives the name, the
+/// others are rows of cells from the top, `.` is a dead cell and `O` a
+/// live one, the top left cell is at the origin
+/// the pattern is read into the z = 0 plane in more dimensions
+pub fn read<const N: usize>(text: &str) -> Result<Pattern<N>, ParsePatternError> {
+    let mut pattern = Pattern::default();
+    let mut y = 0;
+
+    for (n, line) in text.lines().enumerate().map(|(i, l)| (i + 1, l)) {
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.trim_end().to_string()),
+            }
+            continue;
+        }
+
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                'O' | '*' => {
+                    let pos = planar(x as i32, y).ok_or_else(|| {
+                        let reason = format!("the pattern has more than {} dimensions", N);
+                        ParsePatternError::new(n, x + 1, reason)
+                    })?;
+                    pattern.cells.insert(pos);
+                }
+                c if c.is_whitespace() => {}
+                c => {
+                    let reason = format!("unexpected character `{}`", c);
+                    return Err(ParsePatternError::new(n, x + 1, reason));
+                }
+            }
+        }
+        y += 1;
+    }
+
+    Ok(pattern)
+}
+
+/// write a pattern in the plaintext format, the position of the pattern
+/// is lost as the format always starts at the origin
+pub fn write(pattern: &Pattern<2>) -> String {
+    let mut out = String::new();
+
+    if let Some(name) = &pattern.name {
+        writeln!(out, "!Name: {}", name).unwrap();
+    }
+    for comment in pattern.comments.iter() {
+        writeln!(out, "!{}", comment).unwrap();
+    }
+
+    let Some((min, max)) = pattern.bounds() else {
+        return out;
+    };
+
+    write_rows(&mut out, &pattern.cells, (min, max), 'O');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn glider() {
+        let text = "!Name: Glider\n!\n!The smallest spaceship.\n.O.\n..O\nOOO\n";
+        let pattern = read::<2>(text).unwrap();
+
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments, ["", "The smallest spaceship."]);
+        assert_eq!(
+            pattern.cells,
+            HashSet::from([[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]])
+        );
+
+        let err = read::<2>("!c\n.O.\n.o.\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (3, 2));
+
+        let pattern = read::<3>("O\n.O\n").unwrap();
+        assert_eq!(pattern.cells, HashSet::from([[0, 0, 0], [1, 1, 0]]));
+        assert!(read::<1>("OO\n").is_ok());
+        assert_eq!(read::<1>("OO\nO\n").unwrap_err().line(), 2);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut pattern = Pattern::new(HashSet::from([[0, 0], [5, 0], [2, 3], [3, 3]]));
+        pattern.name = Some("test".to_string());
+        pattern.comments = vec!["a comment".to_string()];
+
+        let text = write(&pattern);
+        assert_eq!(text, "!Name: test\n!a comment\nO....O\n.\n.\n..OO\n");
+        assert_eq!(read::<2>(&text).unwrap(), pattern);
+
+        // moved to the origin
+        let moved = Pattern::new(HashSet::from([[-3, 7], [-2, 8]]));
+        let cells = HashSet::from([[0, 0], [1, 1]]);
+        assert_eq!(read::<2>(&write(&moved)).unwrap().cells, cells);
+    }
+}
@@ -0,0 +1,516 @@
+//! the run length encoded format, in two or more dimensions
+
+use std::fmt::Write;
+
+use crate::format::ParsePatternError;
+use crate::format::Pattern;
+use crate::rule::Rule;
+
+// longest line of cells written, as in most RLE files
+const WIDTH: usize = 70;
+
+// the things a run can be made of
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Item {
+    Dead,
+    Live,
+    // go to the start of the next line along an axis, resetting the
+    // axes below it
+    Next(usize),
+    End,
+}
+
+impl Item {
+    fn token(&self) -> String {
+        match *self {
+            Item::Dead => "b".to_string(),
+            Item::Live => "o".to_string(),
+            Item::Next(1) => "$".to_string(),
+            Item::Next(2) => "/".to_string(),
+            Item::Next(axis) => format!("[{}]", axis),
+            Item::End => "!".to_string(),
+        }
+    }
+}
+
+// the axis of a size in the header, x, y, z and w are the first four
+// and a0, a1, ... can name any
+fn axis(key: &str) -> Option<usize> {
+    match key {
+        "x" => Some(0),
+        "y" => Some(1),
+        "z" => Some(2),
+        "w" => Some(3),
+        _ => key.strip_prefix('a')?.parse().ok(),
+    }
+}
+
+fn axis_name(axis: usize) -> String {
+    match axis {
+        0 => "x".to_string(),
+        1 => "y".to_string(),
+        2 => "z".to_string(),
+        3 => "w".to_string(),
+        a => format!("a{}", a),
+    }
+}
+
+/// read a pattern in the run length encoded format, extended to N
+/// dimensions
+///
+/// `#N` and `#C` lines give the name and the comments, `#R x y` or
+/// `#P x y` the position of the top left corner, then comes the header
+/// `x = 3, y = 3, rule = B3/S23` and the rows of cells from the top, `b`
+/// is a dead cell, `o` (or any other letter) a live one, `$` ends a row
+/// and `!` the pattern, each can be preceded by a run count
+///
+/// in more dimensions the header gives the size along z and w, and a4,
+/// a5, ... for the axes after them, `/` ends a plane along z and `[k]`
+/// ends a line along axis k, resetting the axes below it
+/// a pattern with fewer dimensions is read with the extra coordinates
+/// set to 0, so standard RLE is read into the z = 0 plane
+pub fn read<const N: usize>(text: &str) -> Result<Pattern<N>, ParsePatternError> {
+    let mut pattern = Pattern::default();
+    let mut origin = [0; N];
+    let mut lines = text.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+    loop {
+        let Some((n, line)) = lines.next() else {
+            let n = text.lines().count() + 1;
+            return Err(ParsePatternError::new(n, 1, "missing header line"));
+        };
+
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            let column = line.len() - trimmed.len() + 1;
+            read_comment(comment, n, column, &mut pattern, &mut origin)?;
+            continue;
+        }
+
+        read_header(line, n, &mut pattern)?;
+        break;
+    }
+
+    let mut pos = origin;
+    let mut run: Option<i32> = None;
+
+    'lines: for (n, line) in lines {
+        let mut chars = line.chars().enumerate().map(|(i, c)| (i + 1, c));
+
+        while let Some((column, c)) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            if let Some(d) = c.to_digit(10) {
+                run = run
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|r| r.checked_add(d as i32));
+                if run.is_none() {
+                    return Err(ParsePatternError::new(n, column, "run count too large"));
+                }
+                continue;
+            }
+
+            let item = match c {
+                'b' | '.' => Item::Dead,
+                '$' => Item::Next(1),
+                '/' => Item::Next(2),
+                '[' => {
+                    let mut axis = String::new();
+                    let mut closed = false;
+                    for (_, c) in chars.by_ref() {
+                        if c == ']' {
+                            closed = true;
+                            break;
+                        }
+                        axis.push(c);
+                    }
+
+                    match axis.trim().parse() {
+                        Ok(axis) if axis > 0 && closed => Item::Next(axis),
+                        _ => {
+                            let reason = format!("invalid axis `[{}`", axis);
+                            return Err(ParsePatternError::new(n, column, reason));
+                        }
+                    }
+                }
+                '!' => Item::End,
+                c if c.is_ascii_alphabetic() => Item::Live,
+                c => {
+                    let reason = format!("unexpected character `{}`", c);
+                    return Err(ParsePatternError::new(n, column, reason));
+                }
+            };
+
+            let k = run.take().unwrap_or(1);
+            let too_large = || ParsePatternError::new(n, column, "pattern too large");
+            match item {
+                Item::Dead => pos[0] = pos[0].checked_add(k).ok_or_else(too_large)?,
+                Item::Live => {
+                    for _ in 0..k {
+                        pattern.cells.insert(pos);
+                        pos[0] = pos[0].checked_add(1).ok_or_else(too_large)?;
+                    }
+                }
+                Item::Next(axis) if axis >= N => {
+                    let reason = format!("axis {} is outside of {} dimensions", axis, N);
+                    return Err(ParsePatternError::new(n, column, reason));
+                }
+                Item::Next(axis) => {
+                    pos[..axis].copy_from_slice(&origin[..axis]);
+                    pos[axis] = pos[axis].checked_add(k).ok_or_else(too_large)?;
+                }
+                Item::End => break 'lines,
+            }
+        }
+    }
+
+    Ok(pattern)
+}
+
+// a line starting with #, without the #
+fn read_comment<const N: usize>(
+    comment: &str,
+    n: usize,
+    column: usize,
+    pattern: &mut Pattern<N>,
+    origin: &mut [i32; N],
+) -> Result<(), ParsePatternError> {
+    let mut chars = comment.chars();
+    let kind = chars.next();
+    let text = chars.as_str();
+    let text = text.strip_prefix(' ').unwrap_or(text);
+
+    match kind {
+        Some('N') => pattern.name = Some(text.trim_end().to_string()),
+        Some('C' | 'c') => pattern.comments.push(text.trim_end().to_string()),
+        // the axes that aren't given are 0
+        Some('R' | 'P') => {
+            let coords: Result<Vec<i32>, _> = text.split_whitespace().map(|t| t.parse()).collect();
+            match coords {
+                Ok(coords) if coords.len() <= N => origin[..coords.len()].copy_from_slice(&coords),
+                _ => return Err(ParsePatternError::new(n, column, "invalid position")),
+            }
+        }
+        // authors and anything else
+        _ => {}
+    }
+
+    Ok(())
+}
+
+// the header line, `x = 3, y = 3, rule = B3/S23`, the rule can contain
+// commas so it takes the rest of the line
+fn read_header<const N: usize>(
+    line: &str,
+    n: usize,
+    pattern: &mut Pattern<N>,
+) -> Result<(), ParsePatternError> {
+    let column = |byte: usize| line[..byte].chars().count() + 1;
+    let mut sized = [false; 2];
+    let mut start = 0;
+
+    while start < line.len() {
+        let rest = &line[start..];
+        let skip = rest.len() - rest.trim_start().len();
+        let Some(eq) = rest.find('=') else {
+            let reason = "expected `key = value` in the header";
+            return Err(ParsePatternError::new(n, column(start + skip), reason));
+        };
+
+        let key = rest[..eq].trim();
+        let after = &rest[eq + 1..];
+        let len = match after.find(',') {
+            Some(i) if key != "rule" => i,
+            _ => after.len(),
+        };
+        let value = after[..len].trim();
+        let value_column = column(start + eq + 1 + (len - after[..len].trim_start().len()));
+
+        match key {
+            "rule" => {
+                // a bounded grid after the colon isn't supported
+                let rule = value.split(':').next().unwrap_or_default();
+                let rule: Rule = rule.parse().map_err(|e| {
+                    ParsePatternError::new(n, value_column, format!("invalid rule: {}", e))
+                })?;
+                pattern.rule = Some(rule);
+            }
+            "" => {
+                let reason = "expected `key = value` in the header";
+                return Err(ParsePatternError::new(n, column(start + skip), reason));
+            }
+            key => {
+                if let Some(axis) = axis(key) {
+                    let size: i64 = value.parse().map_err(|_| {
+                        let reason = format!("invalid size `{}`", value);
+                        ParsePatternError::new(n, value_column, reason)
+                    })?;
+                    if axis >= N && size > 1 {
+                        let reason = format!("the pattern has more than {} dimensions", N);
+                        return Err(ParsePatternError::new(n, column(start + skip), reason));
+                    }
+                    if axis < 2 {
+                        sized[axis] = true;
+                    }
+                }
+            }
+        }
+
+        start += eq + 1 + len + 1;
+    }
+
+    if !sized[0] || (N > 1 && !sized[1]) {
+        return Err(ParsePatternError::new(n, 1, "header without x and y"));
+    }
+
+    Ok(())
+}
+
+// add n of the item to the runs, merging it with the last run if equal
+fn push(runs: &mut Vec<(i64, Item)>, n: i64, item: Item) {
+    match runs.last_mut() {
+        Some((k, last)) if *last == item => *k += n,
+        _ => runs.push((n, item)),
+    }
+}
+
+/// write a pattern in the run length encoded format, with `#R` giving
+/// the top left corner if it isn't the origin
+/// two-dimensional patterns are standard RLE, the others use the
+/// extensions of read
+pub fn write<const N: usize>(pattern: &Pattern<N>) -> String {
+    let mut out = String::new();
+
+    if let Some(name) = &pattern.name {
+        writeln!(out, "#N {}", name).unwrap();
+    }
+    for comment in pattern.comments.iter() {
+        writeln!(out, "#C {}", comment).unwrap();
+    }
+
+    let (min, max) = pattern.bounds().unwrap_or(([0; N], [-1; N]));
+    if min != [0; N] {
+        let coords: Vec<_> = min.iter().map(|x| x.to_string()).collect();
+        writeln!(out, "#R {}", coords.join(" ")).unwrap();
+    }
+
+    let sizes: Vec<_> = (0..N)
+        .map(|a| format!("{} = {}", axis_name(a), max[a] as i64 - min[a] as i64 + 1))
+        .collect();
+    write!(out, "{}", sizes.join(", ")).unwrap();
+    if let Some(rule) = &pattern.rule {
+        write!(out, ", rule = {}", rule).unwrap();
+    }
+    out.push('\n');
+
+    // the cells in the order they are written, the last axis changes
+    // the slowest
+    let mut cells: Vec<_> = pattern.cells.iter().map(|c| c.map(i64::from)).collect();
+    cells.sort_unstable_by_key(|c| {
+        let mut key = *c;
+        key.reverse();
+        key
+    });
+
+    let min = min.map(i64::from);
+    let mut runs = Vec::new();
+    let mut pos = min;
+
+    for c in cells {
+        // move along the highest axis the cell is on another line of,
+        // then down to its line from the start of the lower axes
+        if let Some(a) = (1..N).rev().find(|&a| c[a] != pos[a]) {
+            push(&mut runs, c[a] - pos[a], Item::Next(a));
+            for k in (1..a).rev() {
+                if c[k] > min[k] {
+                    push(&mut runs, c[k] - min[k], Item::Next(k));
+                }
+            }
+            pos = c;
+            pos[0] = min[0];
+        }
+
+        if c[0] > pos[0] {
+            push(&mut runs, c[0] - pos[0], Item::Dead);
+        }
+        push(&mut runs, 1, Item::Live);
+        pos = c;
+        pos[0] += 1;
+    }
+    push(&mut runs, 1, Item::End);
+
+    let mut width = 0;
+    for (n, item) in runs {
+        let token = if n == 1 {
+            item.token()
+        } else {
+            format!("{}{}", n, item.token())
+        };
+        if width + token.len() > WIDTH {
+            out.push('\n');
+            width = 0;
+        }
+        width += token.len();
+        out.push_str(&token);
+    }
+    out.push('\n');
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::points;
+    use std::collections::HashSet;
+
+    #[test]
+    fn glider() {
+        let text = "#N Glider\n\
+                    #C The smallest spaceship.\n\
+                    #O Richard K. Guy\n\
+                    x = 3, y = 3, rule = B3/S23\n\
+                    bob$2bo$3o!\n";
+        let pattern = read::<2>(text).unwrap();
+
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments, ["The smallest spaceship."]);
+        assert_eq!(pattern.rule, Some(Rule::default()));
+        assert_eq!(
+            pattern.cells,
+            HashSet::from([[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]])
+        );
+    }
+
+    #[test]
+    fn runs() {
+        // counts across lines, empty rows and no rule
+        let text = "x = 15, y = 3\n3o2$\n1\n2b o b o\n!";
+        let pattern = read::<2>(text).unwrap();
+
+        assert_eq!(pattern.rule, None);
+        assert_eq!(
+            pattern.cells,
+            HashSet::from([[0, 0], [1, 0], [2, 0], [12, 2], [14, 2]])
+        );
+
+        let text = "#P -5 7\nx = 1, y = 1, rule = R1,C2,M0,S1..2,B3..3,NM\n";
+        assert!(read::<2>(text).is_err());
+        let pattern = read::<2>("#R -5 7\nx = 2, y = 1, rule = 23/3\n2o!").unwrap();
+        assert_eq!(pattern.cells, HashSet::from([[-5, 7], [-4, 7]]));
+        assert_eq!(pattern.rule, Some(Rule::default()));
+    }
+
+    #[test]
+    fn errors() {
+        let err = read::<2>("#C only comments\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (2, 1));
+
+        let err = read::<2>("x = 3, y = 3, rule = B3/S2a\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (1, 22));
+
+        let err = read::<2>("x = 3, y = three\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (1, 12));
+        assert_eq!(err.to_string(), "1:12: invalid size `three`");
+
+        let err = read::<2>("x = 3, y = 3\nbo$\n2b?o!\n").unwrap_err();
+        assert_eq!((err.line(), err.column()), (3, 3));
+
+        let err = read::<2>("x = 3\n3o!").unwrap_err();
+        assert_eq!((err.line(), err.column()), (1, 1));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut pattern = Pattern::new(HashSet::from([
+            [-3, 2],
+            [-2, 2],
+            [40, 2],
+            [0, 5],
+            [1, 5],
+            [1, 6],
+        ]));
+        pattern.name = Some("test".to_string());
+        pattern.comments = vec!["two".to_string(), "  lines".to_string()];
+        pattern.rule = Some(Rule::generations([2], [3, 4], 5));
+
+        let text = write(&pattern);
+        assert!(text.starts_with("#N test\n#C two\n#C   lines\n#R -3 2\n"));
+        assert!(text.contains("x = 44, y = 5, rule = B2/S34/C5\n"));
+        assert_eq!(read::<2>(&text).unwrap(), pattern);
+
+        // long rows are wrapped
+        let row = Pattern::new((0..200).map(|x| [2 * x, 0]).collect());
+        let text = write(&row);
+        assert!(text.lines().all(|l| l.len() <= WIDTH));
+        assert_eq!(read::<2>(&text).unwrap(), row);
+
+        let empty = Pattern::new(HashSet::new());
+        assert_eq!(read::<2>(&write(&empty)).unwrap(), empty);
+    }
+
+    #[test]
+    fn dimensions() {
+        // two planes of a 3D pattern, the second one starting with an
+        // empty row
+        let text = "x = 3, y = 2, z = 2, rule = B5/S45\n2o$bo/$3o!";
+        let pattern = read::<3>(text).unwrap();
+        assert_eq!(
+            pattern.cells,
+            HashSet::from([
+                [0, 0, 0],
+                [1, 0, 0],
+                [1, 1, 0],
+                [0, 1, 1],
+                [1, 1, 1],
+                [2, 1, 1]
+            ])
+        );
+
+        // higher axes and the position of the corner
+        let text = "#R 1 2 3\nx = 1, y = 1, z = 1, w = 3, a4 = 2\no2[3]o[4]o!";
+        let pattern = read::<5>(text).unwrap();
+        assert_eq!(
+            pattern.cells,
+            HashSet::from([[1, 2, 3, 0, 0], [1, 2, 3, 2, 0], [1, 2, 3, 0, 1]])
+        );
+
+        // standard RLE is embedded into the z = 0 plane
+        let pattern = read::<4>("x = 3, y = 1\n3o!").unwrap();
+        assert_eq!(
+            pattern.cells,
+            HashSet::from([[0, 0, 0, 0], [1, 0, 0, 0], [2, 0, 0, 0]])
+        );
+
+        let err = read::<2>("x = 1, y = 1, z = 2\no/o!").unwrap_err();
+        assert_eq!((err.line(), err.column()), (1, 15));
+        let err = read::<3>("x = 1, y = 1\no[3]o!").unwrap_err();
+        assert_eq!((err.line(), err.column()), (2, 2));
+        let err = read::<5>("x = 1, y = 1\no[3o!").unwrap_err();
+        assert_eq!((err.line(), err.column()), (2, 2));
+    }
+
+    #[test]
+    fn round_trip_dimensions() {
+        let pattern = Pattern::new(points::<3>(60, 4, 1));
+        assert_eq!(read::<3>(&write(&pattern)).unwrap(), pattern);
+
+        let pattern = Pattern::new(points::<4>(100, 4, 2));
+        assert_eq!(read::<4>(&write(&pattern)).unwrap(), pattern);
+
+        let mut pattern = Pattern::new(points::<7>(50, 4, 3));
+        pattern.rule = Some("B4/S3".parse().unwrap());
+        let text = write(&pattern);
+        assert!(text.contains("x = 9, y = 9, z = 9, w = 9, a4 = 9, a5 = 9, a6 = 9"));
+        assert_eq!(read::<7>(&text).unwrap(), pattern);
+
+        let line = Pattern::new(HashSet::from([[-2], [0], [1]]));
+        assert_eq!(write(&line), "#R -2\nx = 4\nob2o!\n");
+        assert_eq!(read::<1>(&write(&line)).unwrap(), line);
+    }
+}
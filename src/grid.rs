@@ -0,0 +1,288 @@
+//! dense bounded universe packed into bits
+
+use std::iter;
+
+use crate::rule::Rule;
+use crate::universe::Universe;
+use crate::Vector;
+
+/// dense bounded universe, cells are packed into bits along axis 0 and
+/// everything outside of 0..size on every axis is dead
+/// the cells of a word are updated together with bit-sliced counters,
+/// supports two-state rules without B0 on the Moore neighborhood, like
+/// HashLife and Tiled
+/// nothing leaves the bounds, so unlike the sparse universes a cycle can't
+/// run out of coordinates
+///
+/// unstable, this can change in any release
+pub struct Grid<const N: usize> {
+    size: [i32; N],
+    words: usize,   // words in a row along axis 0
+    bits: Vec<u64>, // rows ordered by the coordinates of the other axes
+    rule: Rule,
+    width: usize, // bits of the neighbor counters
+    generation: u64,
+}
+
+// add a word of ones to the bit-sliced counters, counter[k] holds bit k
+// of the count of each cell
+fn add(counter: &mut [u64], mut carry: u64) {
+    for c in counter.iter_mut() {
+        if carry == 0 {
+            break;
+        }
+        let t = *c & carry;
+        *c ^= carry;
+        carry = t;
+    }
+}
+
+// the cells whose counter is one of the counts
+fn matching<'a>(counter: &[u64], counts: impl Iterator<Item = &'a usize>) -> u64 {
+    counts
+        .filter(|&&n| n >> counter.len() == 0)
+        .map(|&n| {
+            counter
+                .iter()
+                .enumerate()
+                .fold(!0, |m, (k, &c)| m & if n >> k & 1 == 1 { c } else { !c })
+        })
+        .fold(0, |m, e| m | e)
+}
+
+impl<const N: usize> Grid<N> {
+    /// an empty grid covering 0..size along each axis, playing the
+    /// original game
+    pub fn new(size: [i32; N]) -> Self {
+        Self::with_rule(size, Rule::default())
+    }
+
+    /// an empty grid with another rule
+    /// panics if a size isn't positive or the rule isn't supported
+    pub fn with_rule(size: [i32; N], rule: Rule) -> Self {
+        assert!(N > 0, "a grid needs at least one axis");
+        assert!(size.iter().all(|&s| s > 0), "axis size must be positive");
+        assert!(rule.states == 2, "grids only support two-state rules");
+        assert!(!rule.born(0), "grids don't support B0 rules");
+
+        let words = (size[0] as usize).div_ceil(64);
+        let rows: usize = size[1..].iter().map(|&s| s as usize).product();
+        let neighbors = 3usize.pow(N as u32) - 1;
+
+        Grid {
+            size,
+            words,
+            bits: vec![0; words * rows],
+            rule,
+            width: (usize::BITS - neighbors.leading_zeros()) as usize,
+            generation: 0,
+        }
+    }
+
+    /// the number of cells along each axis
+    pub fn size(&self) -> [i32; N] {
+        self.size
+    }
+
+    // index of the row holding the position, None if it is outside
+    fn row(&self, pos: &Vector<N>) -> Option<usize> {
+        let mut row = 0;
+
+        for a in (1..N).rev() {
+            if !(0..self.size[a]).contains(&pos[a]) {
+                return None;
+            }
+            row = row * self.size[a] as usize + pos[a] as usize;
+        }
+
+        Some(row)
+    }
+
+    // the coordinates of a row, axis 0 is left at 0
+    fn row_pos(&self, mut row: usize) -> Vector<N> {
+        let mut pos = [0; N];
+
+        for (x, &size) in pos.iter_mut().zip(self.size.iter()).skip(1) {
+            *x = (row % size as usize) as i32;
+            row /= size as usize;
+        }
+
+        pos
+    }
+
+    // the rows around a row, itself included, that are inside the grid
+    fn neighbor_rows(&self, row: usize) -> Vec<usize> {
+        let pos = self.row_pos(row);
+
+        (0..3usize.pow(N as u32 - 1))
+            .filter_map(|mut t| {
+                let mut n = pos;
+                for x in n[1..].iter_mut() {
+                    *x += (t % 3) as i32 - 1;
+                    t /= 3;
+                }
+                self.row(&n)
+            })
+            .collect()
+    }
+
+    // bits past the end of the row are always dead
+    fn tail_mask(&self) -> u64 {
+        match self.size[0] % 64 {
+            0 => !0,
+            r => (1 << r) - 1,
+        }
+    }
+}
+
+impl<const N: usize> Universe<N> for Grid<N> {
+    fn get(&self, pos: &Vector<N>) -> bool {
+        if !(0..self.size[0]).contains(&pos[0]) {
+            return false;
+        }
+
+        self.row(pos).is_some_and(|row| {
+            let x = pos[0] as usize;
+            self.bits[row * self.words + x / 64] >> (x % 64) & 1 == 1
+        })
+    }
+
+    // cells outside of the grid can't be created
+    fn set(&mut self, pos: Vector<N>, alive: bool) {
+        if !(0..self.size[0]).contains(&pos[0]) {
+            return;
+        }
+
+        if let Some(row) = self.row(&pos) {
+            let x = pos[0] as usize;
+            let word = &mut self.bits[row * self.words + x / 64];
+            if alive {
+                *word |= 1 << (x % 64);
+            } else {
+                *word &= !(1 << (x % 64));
+            }
+        }
+    }
+
+    fn cycle(&mut self) {
+        let words = self.words;
+        let mut next = vec![0; self.bits.len()];
+        let mut counter = vec![0; self.width];
+
+        for row in 0..self.bits.len() / words {
+            let neighbors = self.neighbor_rows(row);
+
+            for i in 0..words {
+                counter.fill(0);
+
+                for &n in neighbors.iter() {
+                    let line = &self.bits[n * words..(n + 1) * words];
+                    let w = line[i];
+                    let prev = if i > 0 { line[i - 1] } else { 0 };
+                    let succ = if i + 1 < words { line[i + 1] } else { 0 };
+
+                    // the cells to the left and right, and the cell itself
+                    // unless it is the center
+                    add(&mut counter, w << 1 | prev >> 63);
+                    add(&mut counter, w >> 1 | succ << 63);
+                    if n != row {
+                        add(&mut counter, w);
+                    }
+                }
+
+                let live = self.bits[row * words + i];
+                let born = matching(&counter, self.rule.birth.iter());
+                let survive = matching(&counter, self.rule.survival.iter());
+                next[row * words + i] = (live & survive) | (!live & born);
+            }
+
+            next[row * words + words - 1] &= self.tail_mask();
+        }
+
+        self.bits = next;
+        self.generation += 1;
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    fn cells(&self) -> Box<dyn Iterator<Item = Vector<N>> + '_> {
+        let cells = self.bits.iter().enumerate().flat_map(move |(i, &w)| {
+            let mut pos = self.row_pos(i / self.words);
+            let base = (i % self.words * 64) as i32;
+
+            // the set bits of the word
+            iter::successors(Some(w).filter(|&w| w != 0), |&w| {
+                Some(w & (w - 1)).filter(|&w| w != 0)
+            })
+            .map(move |w| {
+                pos[0] = base + w.trailing_zeros() as i32;
+                pos
+            })
+        });
+
+        Box::new(cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::soup;
+    use crate::topology::Topology;
+    use crate::Life;
+    use std::collections::HashSet;
+
+    fn compare<const N: usize>(size: i32, rule: Rule, generations: usize) {
+        let mut grid = Grid::with_rule([size; N], rule.clone());
+        let mut life = Life::<N>::with_rule(rule);
+        life.set_topology(Topology::bounded([size; N]));
+
+        for c in soup([size; N], 42) {
+            grid.create(c);
+            life.create(c);
+        }
+
+        for _ in 0..generations {
+            grid.cycle();
+            life.cycle();
+            let cells: HashSet<_> = grid.cells().collect();
+            assert_eq!(cells, life.cells);
+        }
+    }
+
+    #[test]
+    fn matches_life() {
+        // more than one word per row
+        compare::<2>(70, Rule::default(), 20);
+        compare::<3>(12, "B5/S45".parse().unwrap(), 10);
+    }
+
+    #[test]
+    fn bounds() {
+        let mut grid = Grid::<2>::new([5, 5]);
+        grid.create([0, 0]);
+        grid.create([0, 1]);
+        grid.create([0, 2]);
+        grid.create([-1, 1]);
+        grid.create([5, 1]);
+        assert_eq!(grid.population(), 3);
+        assert!(grid.get(&[0, 1]));
+        assert!(!grid.get(&[-1, 1]));
+
+        grid.cycle();
+        let cells: HashSet<_> = grid.cells().collect();
+        assert_eq!(cells, HashSet::from([[0, 1], [1, 1]]));
+    }
+
+    #[test]
+    #[should_panic(expected = "B0")]
+    fn no_b0() {
+        Grid::<2>::with_rule([5, 5], "B0/S".parse().unwrap());
+    }
+}
@@ -0,0 +1,638 @@
+//! quadtree-like universe with memoized evolution, for large and
+//! regular patterns
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::coord::Coord;
+use crate::coord::OutOfRange;
+use crate::rule::Rule;
+use crate::universe::Universe;
+use crate::Vector;
+
+// index of a node, 0 and 1 are the dead and the live cell
+pub(crate) type Id = u32;
+
+// a node of level k is a cube of 2^k cells along each axis made of 2^N
+// children of level k - 1, bit i of a child's index selects the upper
+// half along axis i
+pub(crate) struct Node {
+    pub(crate) level: u8,
+    pub(crate) children: Box<[Id]>,
+    pub(crate) population: u64,
+}
+
+/// HashLife on 2^N-ary trees, with the quadtree as the N = 2 case
+/// equal subtrees are stored once and the result of advancing a node is
+/// memoized, so repetitive patterns can be advanced by 2^k generations
+/// at a time
+/// supports two-state rules on the Moore neighborhood, without B0
+/// positions are i64 inside, so cells can be given in any coordinate type
+/// nodes are only dropped between steps, once there are more than the
+/// node limit, so a single large step can still use a lot of memory
+///
+/// unstable, this can change in any release
+pub struct HashLife<const N: usize> {
+    pub(crate) nodes: Vec<Node>,
+    canonical: HashMap<Box<[Id]>, Id>,
+    results: HashMap<(Id, u8), Id>, // node advanced by 2^j generations
+    empty: Vec<Id>,                 // empty node of each level
+    node_limit: usize,
+    kept: usize,         // nodes left by the last collection
+    pub(crate) root: Id, // centered on the origin
+    pub(crate) rule: Rule,
+    pub(crate) generation: u64,
+}
+
+// the digits of t in the given base, one for each axis
+fn digits<const N: usize>(mut t: usize, base: usize) -> [usize; N] {
+    let mut ds = [0; N];
+
+    for d in ds.iter_mut() {
+        *d = t % base;
+        t /= base;
+    }
+
+    ds
+}
+
+// inverse of digits
+fn undigits(ds: &[usize], base: usize) -> usize {
+    ds.iter().rev().fold(0, |t, d| t * base + d)
+}
+
+// the dead or the live cell
+fn leaf(population: u64) -> Node {
+    Node {
+        level: 0,
+        children: Box::new([]),
+        population,
+    }
+}
+
+// copy the node and its descendants from old to new, once, and return
+// its new id
+fn copy_node(id: Id, old: &[Node], new: &mut Vec<Node>, ids: &mut HashMap<Id, Id>) -> Id {
+    if let Some(&copy) = ids.get(&id) {
+        return copy;
+    }
+
+    let node = &old[id as usize];
+    let children = node
+        .children
+        .iter()
+        .map(|&c| copy_node(c, old, new, ids))
+        .collect();
+    let copy = new.len() as Id;
+    new.push(Node {
+        level: node.level,
+        children,
+        population: node.population,
+    });
+    ids.insert(id, copy);
+
+    copy
+}
+
+impl<const N: usize> HashLife<N> {
+    /// an empty universe playing the original game
+    pub fn new() -> Self {
+        Self::with_rule(Rule::default())
+    }
+
+    /// an empty universe with another rule on the Moore neighborhood
+    /// panics if the rule isn't supported
+    pub fn with_rule(rule: Rule) -> Self {
+        assert!(rule.states == 2, "hashlife only supports two-state rules");
+        assert!(!rule.born(0), "hashlife doesn't support B0 rules");
+
+        let mut life = HashLife {
+            nodes: vec![leaf(0), leaf(1)],
+            canonical: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![0],
+            node_limit: 1 << 20,
+            kept: 0,
+            root: 0,
+            rule,
+            generation: 0,
+        };
+        life.root = life.empty(3);
+
+        life
+    }
+
+    /// build the tree from a set of live cells
+    pub fn from_cells<C: Coord>(rule: Rule, cells: &HashSet<Vector<N, C>>) -> Self {
+        let mut life = Self::with_rule(rule);
+
+        for &c in cells.iter() {
+            life.create(c);
+        }
+
+        life
+    }
+
+    /// expand the tree into the set of live cells
+    /// panics if a cell has left the coordinate range
+    pub fn to_cells<C: Coord>(&self) -> HashSet<Vector<N, C>> {
+        self.try_to_cells().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// expand the tree into the set of live cells, Err if a cell has left
+    /// the coordinate range
+    pub fn try_to_cells<C: Coord>(&self) -> Result<HashSet<Vector<N, C>>, OutOfRange> {
+        let mut cells = Vec::new();
+        let half = 1i64 << (self.level(self.root) - 1);
+        self.collect(self.root, [-half; N], &mut cells);
+
+        cells
+            .into_iter()
+            .map(|c| crate::vec_cast(&c).ok_or_else(|| OutOfRange::new(&c)))
+            .collect()
+    }
+
+    /// number of nodes stored, including the ones the pattern no longer
+    /// uses
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// when a step starts with more nodes than the limit, the ones the
+    /// pattern no longer uses are dropped, 2^20 by default
+    /// if most nodes are still in use, it waits until their number doubles
+    pub fn set_node_limit(&mut self, limit: usize) {
+        self.node_limit = limit;
+    }
+
+    /// drop the nodes that aren't part of the pattern and the memoized
+    /// results on them, the results between the remaining nodes are kept
+    pub fn collect_garbage(&mut self) {
+        let old = std::mem::replace(&mut self.nodes, vec![leaf(0), leaf(1)]);
+        let mut ids = HashMap::from([(0, 0), (1, 1)]);
+        self.root = copy_node(self.root, &old, &mut self.nodes, &mut ids);
+
+        self.canonical = (2..self.nodes.len())
+            .map(|i| (self.nodes[i].children.clone(), i as Id))
+            .collect();
+        self.results = std::mem::take(&mut self.results)
+            .into_iter()
+            .filter_map(|((id, j), r)| Some(((*ids.get(&id)?, j), *ids.get(&r)?)))
+            .collect();
+        self.empty = vec![0];
+        self.kept = self.nodes.len();
+    }
+
+    /// number of generations since the universe was created
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// number of live cells
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    /// return true if there is a live cell at the position
+    pub fn get<C: Coord>(&self, pos: &Vector<N, C>) -> bool {
+        let mut level = self.level(self.root);
+        let half = 1i64 << (level - 1);
+        let mut local = pos.map(|x| x.to_i64() + half);
+        if local.iter().any(|&x| x < 0 || x >= 2 * half) {
+            return false;
+        }
+
+        let mut id = self.root;
+        while level > 0 {
+            let half = 1i64 << (level - 1);
+            let mut c = 0;
+            for (a, x) in local.iter_mut().enumerate() {
+                if *x >= half {
+                    c |= 1 << a;
+                    *x -= half;
+                }
+            }
+            id = self.nodes[id as usize].children[c];
+            level -= 1;
+        }
+
+        id == 1
+    }
+
+    /// create a live cell at the position
+    pub fn create<C: Coord>(&mut self, pos: Vector<N, C>) {
+        self.set_cell(pos, true);
+    }
+
+    /// kill the cell at the position
+    pub fn kill<C: Coord>(&mut self, pos: Vector<N, C>) {
+        self.set_cell(pos, false);
+    }
+
+    fn set_cell<C: Coord>(&mut self, pos: Vector<N, C>, alive: bool) {
+        let pos = pos.map(C::to_i64);
+        loop {
+            let half = 1i64 << (self.level(self.root) - 1);
+            if pos.iter().all(|&x| -half <= x && x < half) {
+                let level = self.level(self.root);
+                self.root = self.set(self.root, level, pos.map(|x| x + half), alive);
+                return;
+            }
+            // everything outside of the root is dead already
+            if !alive {
+                return;
+            }
+            self.expand();
+        }
+    }
+
+    /// perform a single life cycle
+    pub fn cycle(&mut self) {
+        self.step_pow2(0);
+    }
+
+    /// advance by 2^k generations at once
+    pub fn step_pow2(&mut self, k: u8) {
+        if self.nodes.len() > self.node_limit.max(2 * self.kept) {
+            self.collect_garbage();
+        }
+
+        // the pattern has to be in the center half of the root and the
+        // root large enough, so that the result can hold everything
+        // reachable in 2^k generations
+        while self.level(self.root) < k + 3 || !self.border_empty() {
+            self.expand();
+        }
+        self.expand();
+
+        self.root = self.successor(self.root, k);
+        self.generation += 1 << k;
+    }
+
+    /// advance to the given generation, which can't be in the past
+    pub fn advance_to(&mut self, generation: u64) {
+        assert!(generation >= self.generation, "can't step backwards");
+
+        while self.generation < generation {
+            let diff = generation - self.generation;
+            self.step_pow2(diff.ilog2() as u8);
+        }
+    }
+
+    pub(crate) fn level(&self, id: Id) -> u8 {
+        self.nodes[id as usize].level
+    }
+
+    // the canonical node with the given children
+    pub(crate) fn join(&mut self, children: Vec<Id>) -> Id {
+        if let Some(&id) = self.canonical.get(&children[..]) {
+            return id;
+        }
+
+        let node = Node {
+            level: self.level(children[0]) + 1,
+            population: children
+                .iter()
+                .map(|&c| self.nodes[c as usize].population)
+                .fold(0, u64::saturating_add),
+            children: children.into_boxed_slice(),
+        };
+        let id = self.nodes.len() as Id;
+        self.canonical.insert(node.children.clone(), id);
+        self.nodes.push(node);
+
+        id
+    }
+
+    pub(crate) fn empty(&mut self, level: u8) -> Id {
+        while self.empty.len() <= level as usize {
+            let e = *self.empty.last().unwrap();
+            let id = self.join(vec![e; 1 << N]);
+            self.empty.push(id);
+        }
+
+        self.empty[level as usize]
+    }
+
+    // double the size of the root, keeping it centered
+    fn expand(&mut self) {
+        let root = self.root;
+        assert!(
+            self.level(root) < 63,
+            "the pattern outgrew the i64 coordinate range"
+        );
+
+        let e = self.empty(self.level(root) - 1);
+        let mask = (1 << N) - 1;
+
+        let mut children = Vec::with_capacity(1 << N);
+        for b in 0..1 << N {
+            let mut grandchildren = vec![e; 1 << N];
+            grandchildren[!b & mask] = self.nodes[root as usize].children[b];
+            children.push(self.join(grandchildren));
+        }
+
+        self.root = self.join(children);
+    }
+
+    // true if all live cells are in the center half of the root
+    fn border_empty(&mut self) -> bool {
+        let center = self.subnode(self.root, [1; N]);
+        self.nodes[center as usize].population == self.population()
+    }
+
+    // set a cell at a position relative to the node's corner
+    fn set(&mut self, id: Id, level: u8, mut pos: [i64; N], alive: bool) -> Id {
+        if level == 0 {
+            return alive as Id;
+        }
+
+        let half = 1i64 << (level - 1);
+        let mut c = 0;
+        for (a, x) in pos.iter_mut().enumerate() {
+            if *x >= half {
+                c |= 1 << a;
+                *x -= half;
+            }
+        }
+
+        let mut children = self.nodes[id as usize].children.to_vec();
+        children[c] = self.set(children[c], level - 1, pos, alive);
+        self.join(children)
+    }
+
+    // add the live cells of the node to out, origin is its lowest corner
+    fn collect(&self, id: Id, origin: [i64; N], out: &mut Vec<[i64; N]>) {
+        let node = &self.nodes[id as usize];
+        if node.population == 0 {
+            return;
+        }
+        if node.level == 0 {
+            out.push(origin);
+            return;
+        }
+
+        let half = 1i64 << (node.level - 1);
+        for (c, &child) in node.children.iter().enumerate() {
+            let mut o = origin;
+            for (a, x) in o.iter_mut().enumerate() {
+                *x += half * ((c >> a) & 1) as i64;
+            }
+            self.collect(child, o, out);
+        }
+    }
+
+    // the grandchild at q in the 4^N grid of grandchildren
+    fn grandchild(&self, id: Id, q: [usize; N]) -> Id {
+        let (mut c, mut g) = (0, 0);
+        for (a, x) in q.iter().enumerate() {
+            c |= (x >> 1) << a;
+            g |= (x & 1) << a;
+        }
+
+        let child = self.nodes[id as usize].children[c];
+        self.nodes[child as usize].children[g]
+    }
+
+    // the node one level down made of the grandchildren at p + {0,1}^N,
+    // p in {0,1,2}^N, so p = [1; N] is the centered subnode
+    fn subnode(&mut self, id: Id, p: [usize; N]) -> Id {
+        let children = (0..1 << N)
+            .map(|c| {
+                let mut q = p;
+                for (a, x) in q.iter_mut().enumerate() {
+                    *x += (c >> a) & 1;
+                }
+                self.grandchild(id, q)
+            })
+            .collect();
+
+        self.join(children)
+    }
+
+    // the center of a level k node, one level down, advanced by 2^j
+    // generations, j is at most k - 2
+    fn successor(&mut self, id: Id, j: u8) -> Id {
+        let level = self.level(id);
+        if self.nodes[id as usize].population == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(&result) = self.results.get(&(id, j)) {
+            return result;
+        }
+
+        let result = if level == 2 {
+            self.base(id)
+        } else {
+            // 3^N overlapping nodes one level down, advanced by half of
+            // the time or just cut to their center
+            let full = j == level - 2;
+            let mut partial = Vec::with_capacity(3usize.pow(N as u32));
+            for t in 0..3usize.pow(N as u32) {
+                let sub = self.subnode(id, digits(t, 3));
+                let r = if full {
+                    self.successor(sub, level - 3)
+                } else {
+                    self.subnode(sub, [1; N])
+                };
+                partial.push(r);
+            }
+
+            // combine them into 2^N nodes and advance those
+            let step = if full { level - 3 } else { j };
+            let mut children = Vec::with_capacity(1 << N);
+            for b in 0..1 << N {
+                let b: [usize; N] = digits(b, 2);
+                let parts = (0..1 << N)
+                    .map(|c| {
+                        let c: [usize; N] = digits(c, 2);
+                        let p: Vec<_> = (0..N).map(|a| b[a] + c[a]).collect();
+                        partial[undigits(&p, 3)]
+                    })
+                    .collect();
+                let node = self.join(parts);
+                children.push(self.successor(node, step));
+            }
+
+            self.join(children)
+        };
+
+        self.results.insert((id, j), result);
+        result
+    }
+
+    // advance the 2^N center cells of a level 2 node by one generation
+    fn base(&mut self, id: Id) -> Id {
+        let cells: Vec<bool> = (0..4usize.pow(N as u32))
+            .map(|t| self.grandchild(id, digits(t, 4)) == 1)
+            .collect();
+
+        let children = (0..1 << N)
+            .map(|b| {
+                let s: [usize; N] = digits::<N>(b, 2).map(|x| x + 1);
+                let n = (0..3usize.pow(N as u32))
+                    .map(|o| {
+                        let d: [usize; N] = digits(o, 3);
+                        let q: Vec<_> = (0..N).map(|a| s[a] + d[a] - 1).collect();
+                        undigits(&q, 4)
+                    })
+                    .filter(|&q| q != undigits(&s, 4) && cells[q])
+                    .count();
+
+                let alive = if cells[undigits(&s, 4)] {
+                    self.rule.survives(n)
+                } else {
+                    self.rule.born(n)
+                };
+                alive as Id
+            })
+            .collect();
+
+        self.join(children)
+    }
+}
+
+impl<const N: usize, C: Coord> Universe<N, C> for HashLife<N> {
+    fn get(&self, pos: &Vector<N, C>) -> bool {
+        HashLife::get(self, pos)
+    }
+
+    fn set(&mut self, pos: Vector<N, C>, alive: bool) {
+        self.set_cell(pos, alive);
+    }
+
+    fn cycle(&mut self) {
+        HashLife::cycle(self)
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // big jumps are done 2^k generations at a time
+    fn step_n(&mut self, k: u64) {
+        self.advance_to(self.generation + k);
+    }
+
+    fn population(&self) -> usize {
+        HashLife::population(self) as usize
+    }
+
+    fn cells(&self) -> Box<dyn Iterator<Item = Vector<N, C>> + '_> {
+        Box::new(self.to_cells().into_iter())
+    }
+}
+
+impl<const N: usize> Default for HashLife<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Life;
+
+    #[test]
+    fn matches_life() {
+        // the R-pentomino
+        let mut life = Life::<2>::new();
+        for c in [[1, 0], [2, 0], [0, 1], [1, 1], [1, 2]] {
+            life.create(c);
+        }
+
+        let mut hl = HashLife::from_cells(Rule::default(), &life.cells);
+        let mut jump = HashLife::from_cells(Rule::default(), &life.cells);
+
+        for g in 1..=100 {
+            life.cycle();
+            hl.cycle();
+            assert_eq!(hl.to_cells(), life.cells);
+            assert_eq!(hl.population(), life.cells.len() as u64);
+            assert_eq!(hl.generation(), g);
+        }
+
+        jump.advance_to(100);
+        assert_eq!(jump.to_cells(), life.cells);
+    }
+
+    #[test]
+    fn three_dimensions() {
+        let rule: Rule = "B5/S45".parse().unwrap();
+        let mut life = Life::<3>::with_rule(rule.clone());
+        for c in [
+            [0, 0, 0],
+            [1, 0, 0],
+            [0, 1, 0],
+            [0, 0, 1],
+            [1, 1, 1],
+            [2, 1, 0],
+        ] {
+            life.create(c);
+        }
+
+        let mut hl = HashLife::from_cells(rule, &life.cells);
+        life.step_n(8);
+        hl.step_pow2(3);
+
+        assert_eq!(hl.to_cells(), life.cells);
+    }
+
+    #[test]
+    fn glider_jump() {
+        let glider = [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]];
+        let mut hl = HashLife::<2>::new();
+        for c in glider {
+            hl.create(c);
+        }
+
+        // a glider moves one cell diagonally every 4 generations
+        hl.step_pow2(20);
+        let d = 1 << 18;
+        let moved: HashSet<_> = glider.iter().map(|&[x, y]| [x + d, y + d]).collect();
+        assert_eq!(hl.to_cells(), moved);
+        assert!(hl.get(&[1 + d, d]));
+        assert!(!hl.get(&[1, 0]));
+    }
+
+    #[test]
+    fn coordinate_range() {
+        let mut hl = HashLife::<2>::new();
+        for c in [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]] {
+            hl.create::<i16>(c);
+        }
+
+        hl.step_pow2(20);
+        assert!(hl.try_to_cells::<i16>().is_err());
+        assert_eq!(hl.to_cells::<i64>().len(), 5);
+        assert!(hl.get::<i64>(&[1 + (1 << 18), 1 << 18]));
+    }
+
+    #[test]
+    fn garbage_collection() {
+        // a glider makes new nodes every generation, without collection
+        // they pile up
+        let glider = [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]];
+        let mut hl = HashLife::<2>::new();
+        hl.set_node_limit(500);
+        for c in glider {
+            hl.create(c);
+        }
+
+        for _ in 0..2000 {
+            hl.cycle();
+            assert!(hl.node_count() <= 1000);
+        }
+        let moved: HashSet<_> = glider.iter().map(|&[x, y]| [x + 500, y + 500]).collect();
+        assert_eq!(hl.to_cells(), moved);
+
+        // the results that are kept still give the right answer
+        hl.collect_garbage();
+        let kept = hl.node_count();
+        assert!(kept < 200);
+        hl.step_pow2(4);
+        assert!(hl.get(&[501 + 4, 500 + 4]));
+        assert_eq!(hl.population(), 5);
+    }
+}
@@ -0,0 +1,90 @@
+//! Cellular automata like Conway's Game of Life in any number of
+//! dimensions.
+//!
+//! [`Life`] is the general purpose universe: sparse and infinite, with
+//! any [`Rule`], [`Neighborhood`] and [`Topology`]. The other backends
+//! trade generality for speed on particular kinds of patterns, they all
+//! implement [`Universe`] so code using them can be written once.
+//!
+//! ```
+//! use life::{Life, Universe};
+//!
+//! let mut life = Life::<3>::new();
+//! life.create([0, 1, 0]);
+//! life.create([0, 0, 0]);
+//! life.create([0, -1, 0]);
+//! life.cycle();
+//! assert_eq!(life.population(), 9);
+//! ```
+//!
+//! # Stability
+//!
+//! The items re-exported at the root of the crate, except the ones marked
+//! unstable, are stable: they only change in a breaking way with a new
+//! major version. Those are [`Life`], [`Universe`], [`BoundingBox`],
+//! [`Summary`], [`Snapshot`], [`Evolve`], [`Rule`], [`Ltl`],
+//! [`ParseRuleError`], [`Neighborhood`], [`Topology`], [`Boundary`],
+//! [`Coord`], [`OutOfRange`], [`Pattern`], [`ParsePatternError`],
+//! [`Format`], [`Vector`] and the vector helpers.
+//!
+//! The other backends, [`HashLife`], [`Grid`], [`Tiled`], [`Symmetric`],
+//! [`DynLife`] and [`AnyLife`], and [`DynVector`] are unstable and can
+//! change in any release.
+//!
+//! An item reached through its module has the stability of its re-export.
+//! Of the items that are only in the modules, [`format::read`] and the
+//! readers, writers and headers of [`format::rle`],
+//! [`format::plaintext`], [`format::life105`] and [`format::life106`] are
+//! stable, [`format::macrocell`] and [`format::aoc`] are unstable as they
+//! work on [`HashLife`] and [`DynVector`].
+
+#![warn(missing_docs)]
+
+#[cfg(test)]
+mod bench;
+pub mod coord;
+mod count;
+pub mod dynamic;
+pub mod format;
+pub mod grid;
+pub mod hashlife;
+pub mod life;
+pub mod neighborhood;
+mod pool;
+pub mod rule;
+pub mod symmetry;
+#[cfg(test)]
+mod testing;
+pub mod tiled;
+pub mod topology;
+pub mod universe;
+pub mod vector;
+
+pub use coord::Coord;
+pub use coord::OutOfRange;
+pub use dynamic::AnyLife;
+pub use dynamic::DynLife;
+pub use dynamic::DynVector;
+pub use format::Format;
+pub use format::ParsePatternError;
+pub use format::Pattern;
+pub use grid::Grid;
+pub use hashlife::HashLife;
+pub use life::Life;
+pub use neighborhood::Neighborhood;
+pub use rule::Ltl;
+pub use rule::ParseRuleError;
+pub use rule::Rule;
+pub use symmetry::Symmetric;
+pub use tiled::Tiled;
+pub use topology::Boundary;
+pub use topology::Topology;
+pub use universe::BoundingBox;
+pub use universe::Evolve;
+pub use universe::Snapshot;
+pub use universe::Summary;
+pub use universe::Universe;
+pub use vector::vec_add;
+pub use vector::vec_cast;
+pub use vector::vec_sub;
+pub use vector::Vector;
@@ -0,0 +1,846 @@
+//! the general purpose sparse universe
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::coord::Coord;
+use crate::coord::OutOfRange;
+use crate::count;
+use crate::neighborhood::Neighborhood;
+use crate::pool;
+use crate::rule::Ltl;
+use crate::rule::Rule;
+use crate::topology::Boundary;
+use crate::topology::Topology;
+use crate::universe::Universe;
+use crate::vec_cast;
+use crate::Vector;
+
+/// N dimensional Game of Life representation
+pub struct Life<const N: usize, C: Coord = i32> {
+    pub(crate) cells: HashSet<Vector<N, C>>, // live cells, state 1
+    decaying: HashMap<Vector<N, C>, u8>,     // states above 1 of Generations rules
+    pub(crate) neighbors: Vec<Vector<N, C>>, // cache for neighbor offsets
+    shape: Option<Neighborhood>,             // None for custom offsets
+    pub(crate) rule: Rule,
+    topology: Topology<N>,
+    threads: usize,                 // 1 steps serially, 0 uses every core
+    changed: HashSet<Vector<N, C>>, // cells whose state changed in the last cycle
+    generation: u64,
+}
+
+impl<const N: usize, C: Coord> Life<N, C> {
+    // generate all offsets from a point in N dimensions
+    #[cfg(test)]
+    fn gen_offsets() -> Vec<Vector<N, C>> {
+        Self::shape_offsets(Neighborhood::default())
+    }
+
+    // the offsets of a neighborhood in the coordinate type
+    fn shape_offsets(neighborhood: Neighborhood) -> Vec<Vector<N, C>> {
+        neighborhood
+            .offsets()
+            .iter()
+            .map(|d| vec_cast(d).expect("the neighborhood is larger than the coordinate range"))
+            .collect()
+    }
+
+    /// an empty unbounded universe playing the original game
+    pub fn new() -> Self {
+        Self::with_rule(Rule::default())
+    }
+
+    /// an empty unbounded universe with another rule on the Moore
+    /// neighborhood
+    pub fn with_rule(rule: Rule) -> Self {
+        Self::with_neighborhood(rule, Neighborhood::default())
+    }
+
+    /// an empty unbounded universe with another rule and neighborhood
+    pub fn with_neighborhood(rule: Rule, neighborhood: Neighborhood) -> Self {
+        let mut life = Self::with_offsets(rule, Self::shape_offsets(neighborhood));
+        life.shape = Some(neighborhood);
+        life
+    }
+
+    /// Larger than Life rules, usually with a large range
+    /// Moore and cross neighborhoods are counted with sliding window sums
+    /// in unbounded universes, von Neumann neighborhoods and bounded
+    /// universes look up every offset of every cell, which is much slower
+    /// for large ranges
+    pub fn with_ltl(ltl: &Ltl) -> Self {
+        Self::with_neighborhood(ltl.rule(), ltl.neighborhood)
+    }
+
+    /// use a custom list of neighbor offsets, they are used as given
+    /// so the center or duplicates are counted if they are in the list
+    pub fn with_offsets(rule: Rule, neighbors: Vec<Vector<N, C>>) -> Self {
+        let cells = HashSet::<Vector<N, C>>::new();
+
+        Life {
+            cells,
+            decaying: HashMap::new(),
+            neighbors,
+            shape: None,
+            rule,
+            topology: Topology::default(),
+            threads: 1,
+            changed: HashSet::new(),
+            generation: 0,
+        }
+    }
+
+    /// replace the rule used by the following cycles
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+        self.touch_all();
+    }
+
+    /// number of threads used by cycle, 0 uses one per core
+    /// they are spawned by each cycle that uses them and joined at its
+    /// end, the result doesn't depend on them
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
+    /// change the shape of the universe, existing cells are mapped into it
+    /// and the ones outside of it are dropped
+    pub fn set_topology(&mut self, topology: Topology<N>) {
+        assert!(
+            topology.fits::<C>(),
+            "the universe is larger than the coordinate range"
+        );
+
+        self.topology = topology;
+        self.cells = self
+            .cells
+            .iter()
+            .filter_map(|&c| topology.normalize(c))
+            .collect();
+        self.decaying = self
+            .decaying
+            .iter()
+            .filter_map(|(&c, &s)| Some((topology.normalize(c)?, s)))
+            .collect();
+        self.touch_all();
+    }
+
+    /// the cells whose state changed in the last cycle, or since then
+    /// through create and kill
+    pub fn changed(&self) -> &HashSet<Vector<N, C>> {
+        &self.changed
+    }
+
+    // make the next cycle re-evaluate every cell
+    fn touch_all(&mut self) {
+        self.changed = self
+            .cells
+            .iter()
+            .chain(self.decaying.keys())
+            .copied()
+            .collect();
+    }
+
+    /// number of cycles since the universe was created
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// return true if there is a live cell at the position
+    pub fn get(&self, pos: &Vector<N, C>) -> bool {
+        self.topology
+            .normalize(*pos)
+            .is_some_and(|pos| self.cells.contains(&pos))
+    }
+
+    /// state of the cell at the position, 0 is dead, 1 is alive
+    /// and higher states are decaying cells of Generations rules
+    pub fn state(&self, pos: &Vector<N, C>) -> u8 {
+        match self.topology.normalize(*pos) {
+            Some(pos) if self.cells.contains(&pos) => 1,
+            Some(pos) => self.decaying.get(&pos).copied().unwrap_or(0),
+            None => 0,
+        }
+    }
+
+    /// create a live cell at the position,
+    /// cells outside of a bounded universe can't be created
+    pub fn create(&mut self, pos: Vector<N, C>) {
+        if let Some(pos) = self.topology.normalize(pos) {
+            let decaying = self.decaying.remove(&pos).is_some();
+            if self.cells.insert(pos) || decaying {
+                self.changed.insert(pos);
+            }
+        }
+    }
+
+    /// kill the cell at the position, decaying cells are cleared too
+    pub fn kill(&mut self, pos: Vector<N, C>) {
+        if let Some(pos) = self.topology.normalize(pos) {
+            let decaying = self.decaying.remove(&pos).is_some();
+            if self.cells.remove(&pos) || decaying {
+                self.changed.insert(pos);
+            }
+        }
+    }
+
+    // true if a dead cell with n live neighbors comes alive,
+    // decaying cells can't be born and neither can cells without a live
+    // neighbor, the full cycle never looks at those
+    fn born(&self, pos: &Vector<N, C>, n: usize) -> bool {
+        n > 0 && self.rule.born(n) && !self.decaying.contains_key(pos)
+    }
+
+    /// count the live neighbors of the position
+    pub fn count_neighbors(&self, pos: &Vector<N, C>) -> usize {
+        self.neighbors
+            .iter()
+            .filter_map(|d| self.topology.add(pos, d))
+            .filter(|pos| self.cells.contains(pos))
+            .count()
+    }
+
+    /// perform a life cycle
+    /// only empty cells next to a live one are considered for birth,
+    /// so B0 rules have no effect
+    /// a cell whose state and neighbors didn't change in the last cycle
+    /// stays the same, so when there were few changes only the cells around
+    /// them are re-evaluated
+    /// panics if the pattern reaches the edge of the coordinate range
+    pub fn cycle(&mut self) {
+        if let Err(e) = self.try_cycle() {
+            panic!("{}", e);
+        }
+    }
+
+    /// perform a life cycle, or leave the universe unchanged and return
+    /// an error if the pattern reaches the edge of the coordinate range
+    pub fn try_cycle(&mut self) -> Result<(), OutOfRange> {
+        let tracked = self.changed.len() * self.neighbors.len() < self.cells.len();
+
+        // the tracked cycle looks at the neighbors of the neighbors of the
+        // changed cells, the full one at the neighbors of the live cells
+        if tracked {
+            self.check_range(self.changed.iter(), 2)?;
+        } else {
+            self.check_range(self.cells.iter(), 1)?;
+        }
+
+        let (born, died) = if tracked {
+            self.flips_tracked()
+        } else {
+            let unbounded = self.topology.is_unbounded();
+            let next = match self.shape {
+                Some(Neighborhood::Moore(r)) if r > 1 && unbounded => {
+                    self.next_windowed(count::box_sums(&self.cells, r as i64))
+                }
+                Some(Neighborhood::Cross(r)) if r > 1 && unbounded => {
+                    self.next_windowed(count::cross_sums(&self.cells, r as i64))
+                }
+                _ if pool::threads(self.threads) > 1 => self.next_parallel(),
+                _ => self.next_offsets(),
+            };
+            let born = next.difference(&self.cells).copied().collect();
+            let died = self.cells.difference(&next).copied().collect();
+            (born, died)
+        };
+
+        // decaying cells change every cycle
+        let mut changed: HashSet<_> = self.decaying.keys().copied().collect();
+        changed.extend(born.iter().chain(died.iter()));
+
+        self.decay(&died);
+        for c in died.iter() {
+            self.cells.remove(c);
+        }
+        self.cells.extend(born);
+        self.changed = changed;
+        self.generation += 1;
+
+        Ok(())
+    }
+
+    // Err if a position `steps` neighbor offsets away from one of the
+    // cells is outside the coordinate range, on the unbounded axes
+    fn check_range<'a>(
+        &self,
+        mut cells: impl Iterator<Item = &'a Vector<N, C>>,
+        steps: i64,
+    ) -> Result<(), OutOfRange> {
+        let reach = self
+            .neighbors
+            .iter()
+            .flatten()
+            .map(|x| x.to_i64().abs())
+            .max()
+            .unwrap_or(0);
+        let inside = C::MIN.to_i64() + reach * steps..=C::MAX.to_i64() - reach * steps;
+        let axes = self.topology.axes();
+
+        match cells.find(|c| {
+            (0..N).any(|a| axes[a] == Boundary::Unbounded && !inside.contains(&c[a].to_i64()))
+        }) {
+            Some(c) => Err(OutOfRange::new(c)),
+            None => Ok(()),
+        }
+    }
+
+    // the cells that are born and the ones that die, looking only at the
+    // cells that changed and the cells they are neighbors of
+    fn flips_tracked(&self) -> (Vec<Vector<N, C>>, Vec<Vector<N, C>>) {
+        let mut candidates = self.changed.clone();
+        for c in self.changed.iter() {
+            let around = self
+                .neighbors
+                .iter()
+                .flat_map(|d| self.topology.sources(c, d));
+            candidates.extend(around);
+        }
+
+        candidates
+            .into_iter()
+            .filter(|pos| {
+                self.alive_next(pos, self.count_neighbors(pos)) != self.cells.contains(pos)
+            })
+            .partition(|pos| !self.cells.contains(pos))
+    }
+
+    // large Moore and cross neighborhoods are counted with sliding window
+    // sums, the sums include the cell itself
+    fn next_windowed(&self, mut sums: HashMap<Vector<N, C>, usize>) -> HashSet<Vector<N, C>> {
+        for c in self.cells.iter() {
+            *sums.get_mut(c).unwrap() -= 1;
+        }
+
+        self.next_from_counts(&sums)
+    }
+
+    // every live cell adds one to the count of each cell it neighbors
+    fn next_offsets(&self) -> HashSet<Vector<N, C>> {
+        let counts = count::scatter(&self.cells, &self.neighbors, &self.topology);
+        self.next_from_counts(&counts)
+    }
+
+    // the counts are scattered on several threads and split into shards,
+    // which are then turned into live cells in parallel
+    fn next_parallel(&self) -> HashSet<Vector<N, C>> {
+        let threads = pool::threads(self.threads);
+        let shards = count::scatter_parallel(&self.cells, &self.neighbors, &self.topology, threads);
+
+        let parts = pool::run(threads, shards.len(), |s| {
+            shards[s]
+                .iter()
+                .filter(|(pos, &n)| self.alive_next(pos, n))
+                .map(|(&pos, _)| pos)
+                .collect::<Vec<_>>()
+        });
+        let mut next: HashSet<_> = parts.into_iter().flatten().collect();
+
+        if self.rule.survives(0) {
+            let lonely = self
+                .cells
+                .iter()
+                .filter(|c| !shards[count::shard(c, shards.len())].contains_key(*c));
+            next.extend(lonely);
+        }
+
+        next
+    }
+
+    // the live cells of the next generation from the live neighbor counts,
+    // cells missing from the counts have no live neighbors
+    fn next_from_counts(&self, counts: &HashMap<Vector<N, C>, usize>) -> HashSet<Vector<N, C>> {
+        let mut next: HashSet<_> = counts
+            .iter()
+            .filter(|(pos, &n)| self.alive_next(pos, n))
+            .map(|(&pos, _)| pos)
+            .collect();
+
+        if self.rule.survives(0) {
+            let lonely = self.cells.iter().filter(|c| !counts.contains_key(*c));
+            next.extend(lonely);
+        }
+
+        next
+    }
+
+    // true if the cell at pos with n live neighbors is alive next
+    fn alive_next(&self, pos: &Vector<N, C>, n: usize) -> bool {
+        if self.cells.contains(pos) {
+            self.rule.survives(n)
+        } else {
+            self.born(pos, n)
+        }
+    }
+
+    // advance the decaying cells and start decaying the live cells that
+    // died, two-state rules have nothing to do here
+    fn decay(&mut self, died: &[Vector<N, C>]) {
+        if self.rule.states <= 2 {
+            return;
+        }
+
+        let states = self.rule.states;
+        let mut decaying: HashMap<_, _> = self
+            .decaying
+            .drain()
+            .filter(|&(_, s)| s + 1 < states)
+            .map(|(pos, s)| (pos, s + 1))
+            .collect();
+        decaying.extend(died.iter().map(|&c| (c, 2)));
+
+        self.decaying = decaying;
+    }
+}
+
+impl<const N: usize, C: Coord> Universe<N, C> for Life<N, C> {
+    fn get(&self, pos: &Vector<N, C>) -> bool {
+        Life::get(self, pos)
+    }
+
+    fn set(&mut self, pos: Vector<N, C>, alive: bool) {
+        if alive {
+            Life::create(self, pos)
+        } else {
+            Life::kill(self, pos)
+        }
+    }
+
+    fn cycle(&mut self) {
+        Life::cycle(self)
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn cells(&self) -> Box<dyn Iterator<Item = Vector<N, C>> + '_> {
+        Box::new(self.cells.iter().copied())
+    }
+}
+
+impl<const N: usize, C: Coord> Default for Life<N, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::soup;
+
+    #[test]
+    fn gen_offsets() {
+        let os = Life::<2>::gen_offsets();
+        assert_eq!(os.len(), 3usize.pow(2) - 1);
+
+        let os = Life::<5>::gen_offsets();
+        assert_eq!(os.len(), 3usize.pow(5) - 1);
+    }
+
+    #[test]
+    fn count_neighbors() {
+        let mut l = Life::<3>::new();
+        l.create([0, 0, 0]);
+        l.create([1, 0, 0]);
+        l.create([0, -1, -1]);
+
+        assert_eq!(l.count_neighbors(&[0, 0, 0]), 2);
+    }
+
+    #[test]
+    fn rod() {
+        let mut life = Life::<2>::new();
+        life.create([0, 1]);
+        life.create([0, 0]);
+        life.create([0, -1]);
+
+        let cells = life.cells.clone();
+
+        life.cycle();
+        life.cycle();
+
+        assert_eq!(cells, life.cells);
+    }
+
+    #[test]
+    fn square() {
+        let mut life = Life::<2>::new();
+        life.create([0, 0]);
+        life.create([0, 1]);
+        life.create([1, 0]);
+        life.create([1, 1]);
+
+        let cells = life.cells.clone();
+
+        life.cycle();
+
+        assert_eq!(cells, life.cells);
+    }
+
+    #[test]
+    fn seeds() {
+        // B2/S
+        let mut life = Life::<2>::with_rule(Rule::new([2], []));
+        life.create([0, 0]);
+        life.create([1, 0]);
+
+        life.cycle();
+
+        let expected = HashSet::from([[0, 1], [1, 1], [0, -1], [1, -1]]);
+        assert_eq!(life.cells, expected);
+    }
+
+    #[test]
+    fn von_neumann() {
+        // a plus grows into a diamond under B1/S with the von Neumann neighborhood
+        let rule = Rule::new([1], [0, 1, 2, 3, 4]);
+        let mut life = Life::<2>::with_neighborhood(rule, Neighborhood::VonNeumann(1));
+        life.create([0, 0]);
+
+        life.cycle();
+
+        let expected = HashSet::from([[0, 0], [1, 0], [-1, 0], [0, 1], [0, -1]]);
+        assert_eq!(life.cells, expected);
+    }
+
+    #[test]
+    fn larger_than_life() {
+        // the window sums must agree with plain offset lookups
+        let bosco: Ltl = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        let mut fast = Life::<2>::with_ltl(&bosco);
+        let mut slow = Life::<2>::with_offsets(bosco.rule(), Neighborhood::Moore(5).offsets());
+        for c in soup([20, 20], 12345) {
+            fast.create(c);
+            slow.create(c);
+        }
+
+        for _ in 0..5 {
+            fast.cycle();
+            slow.cycle();
+            assert_eq!(fast.cells, slow.cells);
+        }
+
+        let cross: Ltl = "R4,C0,M0,S2..4,B3..4,N+".parse().unwrap();
+        let mut fast = Life::<2>::with_ltl(&cross);
+        let mut slow = Life::<2>::with_offsets(cross.rule(), Neighborhood::Cross(4).offsets());
+        for c in soup([20, 20], 99) {
+            fast.create(c);
+            slow.create(c);
+        }
+
+        for _ in 0..5 {
+            fast.cycle();
+            slow.cycle();
+            assert_eq!(fast.cells, slow.cells);
+        }
+        assert!(!fast.cells.is_empty());
+    }
+
+    #[test]
+    fn generations() {
+        // every count gives birth, cells decay through one extra state
+        let rule = Rule::generations(1..=8, [], 3);
+        let mut life = Life::<2>::with_rule(rule);
+        life.create([0, 0]);
+
+        life.cycle();
+        assert_eq!(life.cells.len(), 8);
+        assert_eq!(life.state(&[0, 0]), 2);
+
+        // the center has 8 live neighbors but it is still decaying
+        life.cycle();
+        assert_eq!(life.state(&[0, 0]), 0);
+        assert_eq!(life.state(&[1, 1]), 2);
+        assert_eq!(life.state(&[2, 2]), 1);
+        assert_eq!(life.cells.len(), 16);
+    }
+
+    #[test]
+    fn torus() {
+        let mut life = Life::<2>::new();
+        life.set_topology(Topology::torus([8, 6]));
+
+        // a glider crossing both seams
+        for pos in [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]] {
+            life.create(pos);
+        }
+        let cells = life.cells.clone();
+
+        // it moves by one cell diagonally every 4 generations
+        for _ in 0..4 * 24 {
+            life.cycle();
+            assert_eq!(life.cells.len(), 5);
+        }
+        assert_eq!(cells, life.cells);
+
+        assert!(life.get(&[9, 6]));
+        life.create([-1, -1]);
+        assert!(life.cells.contains(&[7, 5]));
+    }
+
+    #[test]
+    fn dead_boundary() {
+        let mut life = Life::<2>::new();
+        life.set_topology(Topology::bounded([5, 5]));
+        life.create([0, 0]);
+        life.create([0, 1]);
+        life.create([0, 2]);
+        life.create([-1, 1]);
+        assert_eq!(life.cells.len(), 3);
+
+        // the rod can't turn as the cell at x = -1 can't be born
+        life.cycle();
+        assert_eq!(life.cells, HashSet::from([[0, 1], [1, 1]]));
+    }
+
+    #[test]
+    fn reflect_boundary() {
+        let pattern = [[0, 0], [0, 1], [1, 1], [2, 1], [1, 3]];
+
+        let mut reflected = Life::<2>::new();
+        reflected.set_topology(Topology::new([Boundary::Reflect(50), Boundary::Unbounded]));
+
+        // the mirror image across the boundary
+        let mut mirrored = Life::<2>::new();
+        for [x, y] in pattern {
+            reflected.create([x, y]);
+            mirrored.create([x, y]);
+            mirrored.create([-x - 1, y]);
+        }
+
+        for _ in 0..10 {
+            reflected.cycle();
+            mirrored.cycle();
+            let half: HashSet<_> = mirrored
+                .cells
+                .iter()
+                .filter(|c| c[0] >= 0)
+                .copied()
+                .collect();
+            assert_eq!(reflected.cells, half);
+        }
+    }
+
+    #[test]
+    fn klein_bottle() {
+        let mut life = Life::<2>::new();
+        life.set_topology(Topology::klein_bottle(6, 5));
+        life.create([0, 0]);
+        life.create([0, 1]);
+        life.create([0, 2]);
+
+        // the rod turns across the twisted seam, so its left end is mirrored
+        life.cycle();
+        assert_eq!(life.cells, HashSet::from([[5, 3], [0, 1], [1, 1]]));
+
+        life.cycle();
+        assert_eq!(life.cells, HashSet::from([[0, 0], [0, 1], [0, 2]]));
+    }
+
+    #[test]
+    fn parallel() {
+        // a small soup in 4D, with a rule that keeps it busy
+        let mut serial = Life::<4>::with_rule(Rule::generations([4], [0, 3, 4], 4));
+        for c in soup([6, 6, 2, 1], 7) {
+            serial.create(c);
+        }
+
+        let mut parallel = Life::<4>::with_rule(serial.rule.clone());
+        for &c in serial.cells.iter() {
+            parallel.create(c);
+        }
+        parallel.set_threads(4);
+
+        for _ in 0..6 {
+            serial.cycle();
+            parallel.cycle();
+            assert_eq!(serial.cells, parallel.cells);
+            assert_eq!(serial.decaying, parallel.decaying);
+        }
+        assert!(!serial.cells.is_empty());
+    }
+
+    #[test]
+    fn activity() {
+        let mut life = Life::<2>::new();
+
+        // a blinker next to a block
+        for c in [[0, -1], [0, 0], [0, 1], [5, 0], [5, 1], [6, 0], [6, 1]] {
+            life.create(c);
+        }
+        assert_eq!(life.changed().len(), 7);
+
+        life.cycle();
+        let expected = HashSet::from([[0, -1], [0, 1], [-1, 0], [1, 0]]);
+        assert_eq!(*life.changed(), expected);
+
+        life.cycle();
+        assert_eq!(*life.changed(), expected);
+        assert_eq!(life.cells.len(), 7);
+        assert!(life.get(&[0, 1]));
+    }
+
+    #[test]
+    fn tracked_matches_full() {
+        // the R-pentomino, with lots of still lifes after a while, a
+        // Generations rule where cells decay and a B0 rule, where B0 has
+        // no effect on either path
+        for rule in [
+            Rule::default(),
+            Rule::generations([3], [2, 3], 4),
+            Rule::new([0, 3], [2, 3]),
+        ] {
+            let mut tracked = Life::<2>::with_rule(rule.clone());
+            let mut full = Life::<2>::with_rule(rule);
+            for c in [[1, 0], [2, 0], [0, 1], [1, 1], [1, 2]] {
+                tracked.create(c);
+                full.create(c);
+            }
+
+            for _ in 0..300 {
+                tracked.cycle();
+                full.touch_all();
+                full.cycle();
+                assert_eq!(tracked.cells, full.cells);
+                assert_eq!(tracked.decaying, full.decaying);
+            }
+        }
+
+        // isolated cells under B0, killing one must not give birth to the
+        // empty cells around it
+        let mut tracked = Life::<2>::with_rule(Rule::new([0], 0..=8));
+        for x in 0..40 {
+            tracked.create([x * 3, 0]);
+        }
+        tracked.cycle();
+        tracked.kill([30, 0]);
+        let mut full = Life::<2>::with_rule(Rule::new([0], 0..=8));
+        full.cells = tracked.cells.clone();
+
+        tracked.cycle();
+        full.touch_all();
+        full.cycle();
+        assert_eq!(tracked.cells, full.cells);
+        assert_eq!(tracked.cells.len(), 39);
+    }
+
+    #[test]
+    fn custom_offsets() {
+        // each cell only looks at its right neighbor, so the pattern moves left
+        let mut life = Life::<2>::with_offsets(Rule::new([1], []), vec![[1, 0]]);
+        life.create([0, 0]);
+
+        life.cycle();
+
+        assert_eq!(life.cells, HashSet::from([[-1, 0]]));
+    }
+
+    // the next generation of a bounded universe from count_neighbors, cell
+    // by cell over the whole box
+    fn next_by_lookup(life: &Life<2>, sizes: [i32; 2]) -> HashSet<Vector<2>> {
+        let mut next = HashSet::new();
+        for x in 0..sizes[0] {
+            for y in 0..sizes[1] {
+                if life.alive_next(&[x, y], life.count_neighbors(&[x, y])) {
+                    next.insert([x, y]);
+                }
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn folded_offsets() {
+        // near a reflecting or twisted edge the cell with c as its neighbor
+        // at offset d isn't c - d
+        let mut life = Life::<2>::with_offsets(Rule::new([1], [1]), vec![[-1, 0]]);
+        life.set_topology(Topology::new([Boundary::Reflect(5), Boundary::Unbounded]));
+        life.create([0, 0]);
+        assert_eq!(life.count_neighbors(&[0, 0]), 1);
+        life.cycle();
+        assert_eq!(life.cells, HashSet::from([[0, 0], [1, 0]]));
+
+        let mut life = Life::<2>::with_offsets(Rule::new([1], []), vec![[1, 1]]);
+        life.set_topology(Topology::klein_bottle(6, 5));
+        life.create([0, 1]);
+        life.cycle();
+        assert_eq!(life.cells, HashSet::from([[5, 2]]));
+
+        let sizes = [7, 6];
+        let topologies = [
+            Topology::new([Boundary::Reflect(7), Boundary::Reflect(6)]),
+            Topology::klein_bottle(7, 6),
+            Topology::projective_plane(7, 6),
+            Topology::new([Boundary::Twist(7, 1), Boundary::Reflect(6)]),
+        ];
+        let offsets = vec![[1, 0], [2, 1], [-1, 2], [0, -3]];
+
+        for topology in topologies {
+            for threads in [1, 3] {
+                let mut life = Life::<2>::with_offsets(Rule::new([2], [1, 2]), offsets.clone());
+                life.set_topology(topology);
+                life.set_threads(threads);
+                for c in soup(sizes, 5) {
+                    life.create(c);
+                }
+
+                for _ in 0..8 {
+                    let expected = next_by_lookup(&life, sizes);
+
+                    // the cells around the changes, with every cell changed
+                    life.touch_all();
+                    let (born, died) = life.flips_tracked();
+                    let tracked: HashSet<_> = life
+                        .cells
+                        .iter()
+                        .filter(|c| !died.contains(c))
+                        .chain(born.iter())
+                        .copied()
+                        .collect();
+                    assert_eq!(tracked, expected, "{:?}", topology);
+
+                    // every cell is evaluated, not only the changed ones
+                    life.touch_all();
+                    life.cycle();
+                    assert_eq!(life.cells, expected, "{:?}", topology);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn coordinate_range() {
+        let glider: [[i64; 2]; 5] = [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]];
+
+        // a glider running into the edge of the i16 range stops with an
+        // error instead of wrapping around
+        let mut small = Life::<2, i16>::new();
+        for [x, y] in glider {
+            small.create([x as i16 + 32700, y as i16 + 32700]);
+        }
+        let err = loop {
+            let before = small.cells.clone();
+            if let Err(e) = small.try_cycle() {
+                assert_eq!(small.cells, before);
+                break e;
+            }
+        };
+        assert!(err.position().iter().all(|&x| x > 32700));
+
+        // the same glider far away with i64 coordinates
+        let far = 1 << 40;
+        let mut large = Life::<2, i64>::new();
+        for [x, y] in glider {
+            large.create([x + far, y + far]);
+        }
+        large.step_n(8);
+        assert!(large.get(&[far + 3, far + 2]));
+        assert_eq!(large.cells.len(), 5);
+    }
+}
@@ -0,0 +1,110 @@
+//! shapes of the region whose live cells are counted
+
+use crate::Vector;
+
+/// shape of the region around a cell whose live cells are counted
+/// the range r is measured in cells along an axis
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// every cell within chebyshev distance r, the full cube for r = 1
+    Moore(u32),
+    /// every cell within manhattan distance r
+    VonNeumann(u32),
+    /// the cells along the axes up to distance r
+    Cross(u32),
+}
+
+impl Neighborhood {
+    /// the range r of the shape
+    pub fn range(&self) -> u32 {
+        match *self {
+            Neighborhood::Moore(r) | Neighborhood::VonNeumann(r) | Neighborhood::Cross(r) => r,
+        }
+    }
+
+    // true if the offset belongs to the shape, the center always does
+    fn contains(&self, offset: &[i32]) -> bool {
+        let r = self.range() as i32;
+        match self {
+            Neighborhood::Moore(_) => offset.iter().all(|x| x.abs() <= r),
+            Neighborhood::VonNeumann(_) => offset.iter().map(|x| x.abs()).sum::<i32>() <= r,
+            Neighborhood::Cross(_) => offset.iter().filter(|&&x| x != 0).count() <= 1,
+        }
+    }
+
+    /// generate all offsets of the shape in N dimensions, without the center
+    pub fn offsets<const N: usize>(&self) -> Vec<Vector<N>> {
+        self.offsets_dyn(N)
+            .into_iter()
+            .map(|x| x.try_into().unwrap())
+            .collect()
+    }
+
+    /// the offsets for a number of dimensions only known at runtime
+    /// it's not fast but it doesn't need to be as it is only run once
+    pub fn offsets_dyn(&self, dims: usize) -> Vec<Vec<i32>> {
+        let r = self.range() as i32;
+
+        // 1D
+        let mut ns: Vec<Vec<i32>> = (-r..=r).map(|d| vec![d]).collect();
+
+        for _ in 1..dims {
+            let mut new = Vec::new();
+
+            for n in ns.iter_mut() {
+                // generate all permutations
+                for d in -r..=r {
+                    let mut nn = n.clone();
+                    nn.push(d);
+                    new.push(nn);
+                }
+            }
+
+            ns = new;
+        }
+
+        // cut out the shape and remove the center point
+        ns.into_iter()
+            .filter(|v| self.contains(v))
+            .filter(|v| v.iter().any(|&x| x != 0))
+            .collect()
+    }
+}
+
+impl Default for Neighborhood {
+    fn default() -> Self {
+        Neighborhood::Moore(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(
+            Neighborhood::Moore(2).offsets::<2>().len(),
+            5usize.pow(2) - 1
+        );
+        assert_eq!(
+            Neighborhood::Moore(1).offsets::<4>().len(),
+            3usize.pow(4) - 1
+        );
+        assert_eq!(Neighborhood::VonNeumann(1).offsets::<5>().len(), 2 * 5);
+        assert_eq!(Neighborhood::VonNeumann(3).offsets::<2>().len(), 2 * 3 * 4);
+        assert_eq!(Neighborhood::Cross(3).offsets::<3>().len(), 2 * 3 * 3);
+    }
+
+    #[test]
+    fn shapes() {
+        let vn = Neighborhood::VonNeumann(2).offsets::<2>();
+        assert!(vn.contains(&[1, 1]));
+        assert!(vn.contains(&[0, -2]));
+        assert!(!vn.contains(&[2, 1]));
+
+        let cross = Neighborhood::Cross(2).offsets::<2>();
+        assert!(cross.contains(&[-2, 0]));
+        assert!(!cross.contains(&[1, 1]));
+    }
+}
@@ -0,0 +1,469 @@
+//! birth and survival rules and their rulestrings
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::neighborhood::Neighborhood;
+
+/// outer totalistic rule: which neighbor counts give birth to a dead cell
+/// and which let a live cell survive
+/// counts go up to the size of the neighborhood, 3^N - 1 for the full cube
+/// with more than two states (Generations rules) a live cell that doesn't
+/// survive decays through the states 2..states before it is dead, decaying
+/// cells are not counted as neighbors and can't be born
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    /// the neighbor counts that give birth to a dead cell
+    pub birth: BTreeSet<usize>,
+    /// the neighbor counts that let a live cell survive
+    pub survival: BTreeSet<usize>,
+    /// number of states including dead and alive, 2 for most rules
+    pub states: u8,
+}
+
+impl Rule {
+    /// a two-state rule
+    pub fn new<B, S>(birth: B, survival: S) -> Self
+    where
+        B: IntoIterator<Item = usize>,
+        S: IntoIterator<Item = usize>,
+    {
+        Self::generations(birth, survival, 2)
+    }
+
+    /// a rule with decaying cells, states counts the dead and live state too
+    pub fn generations<B, S>(birth: B, survival: S, states: u8) -> Self
+    where
+        B: IntoIterator<Item = usize>,
+        S: IntoIterator<Item = usize>,
+    {
+        assert!(states >= 2, "a rule needs at least two states");
+
+        Rule {
+            birth: birth.into_iter().collect(),
+            survival: survival.into_iter().collect(),
+            states,
+        }
+    }
+
+    /// the original game, B3/S23
+    pub fn conway() -> Self {
+        Self::new([3], [2, 3])
+    }
+
+    /// true if a dead cell with n live neighbors comes alive
+    pub fn born(&self, n: usize) -> bool {
+        self.birth.contains(&n)
+    }
+
+    /// true if a live cell with n live neighbors stays alive
+    pub fn survives(&self, n: usize) -> bool {
+        self.survival.contains(&n)
+    }
+
+    // the older notation with survival first and no letters, "23/3",
+    // Generations rules have the number of states as a third part
+    pub(crate) fn survival_first(&self) -> String {
+        let mut s = format!("{}/{}", Counts(&self.survival), Counts(&self.birth));
+        if self.states > 2 {
+            s += &format!("/{}", self.states);
+        }
+        s
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::conway()
+    }
+}
+
+/// error produced when parsing a rulestring, names the offending token
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseRuleError {
+    token: String,
+    reason: &'static str,
+}
+
+impl ParseRuleError {
+    fn new(token: &str, reason: &'static str) -> Self {
+        ParseRuleError {
+            token: token.to_string(),
+            reason,
+        }
+    }
+
+    /// the part of the rulestring that could not be parsed
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: `{}`", self.reason, self.token)
+    }
+}
+
+impl Error for ParseRuleError {}
+
+// parse a list of neighbor counts, either single digits ("23")
+// or comma separated numbers for large neighborhoods ("4,5,12")
+// a single large count is written with a trailing comma ("12,")
+fn parse_counts(s: &str) -> Result<BTreeSet<usize>, ParseRuleError> {
+    if s.contains(',') {
+        s.split(',')
+            .filter(|t| !t.is_empty())
+            .map(|t| {
+                t.trim()
+                    .parse()
+                    .map_err(|_| ParseRuleError::new(t, "invalid neighbor count"))
+            })
+            .collect()
+    } else {
+        s.chars()
+            .map(|c| {
+                c.to_digit(10)
+                    .map(|d| d as usize)
+                    .ok_or_else(|| ParseRuleError::new(&c.to_string(), "invalid neighbor count"))
+            })
+            .collect()
+    }
+}
+
+// parse the number of states of a Generations rule, "C4" or "4"
+fn parse_states(s: &str) -> Result<u8, ParseRuleError> {
+    let n = s.strip_prefix(['C', 'c']).unwrap_or(s);
+    match n.parse() {
+        Ok(states) if states >= 2 => Ok(states),
+        _ => Err(ParseRuleError::new(s, "invalid number of states")),
+    }
+}
+
+// write counts as digits if they all fit, comma separated otherwise
+fn fmt_counts(f: &mut fmt::Formatter, counts: &BTreeSet<usize>) -> fmt::Result {
+    if counts.iter().all(|&n| n < 10) {
+        counts.iter().try_for_each(|n| write!(f, "{}", n))
+    } else {
+        let list: Vec<_> = counts.iter().map(|n| n.to_string()).collect();
+        write!(f, "{}", list.join(","))?;
+        if counts.len() == 1 {
+            write!(f, ",")?;
+        }
+        Ok(())
+    }
+}
+
+// displays a list of counts the way fmt_counts writes it
+struct Counts<'a>(&'a BTreeSet<usize>);
+
+impl fmt::Display for Counts<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_counts(f, self.0)
+    }
+}
+
+// accepts "B3/S23", "S23/B3", the older "23/3" (survival first)
+// and comma separated counts like "B5,6,7/S4,5,12"
+// Generations rules have the number of states as a third part,
+// "B2/S345/C4" or "345/2/4"
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<_> = s.trim().split('/').collect();
+        let states = match parts.len() {
+            2 => 2,
+            3 => parse_states(parts.pop().unwrap())?,
+            _ => {
+                return Err(ParseRuleError::new(
+                    s,
+                    "expected two or three parts separated by '/'",
+                ))
+            }
+        };
+
+        let mut birth = None;
+        let mut survival = None;
+        let prefixed = parts
+            .iter()
+            .filter(|p| p.starts_with(['B', 'b', 'S', 's']))
+            .count();
+
+        match prefixed {
+            0 => {
+                survival = Some(parse_counts(parts[0])?);
+                birth = Some(parse_counts(parts[1])?);
+            }
+            2 => {
+                for part in parts {
+                    let (slot, counts) = match part.split_at(1) {
+                        ("B" | "b", counts) => (&mut birth, counts),
+                        (_, counts) => (&mut survival, counts),
+                    };
+                    if slot.is_some() {
+                        return Err(ParseRuleError::new(part, "duplicate part"));
+                    }
+                    *slot = Some(parse_counts(counts)?);
+                }
+            }
+            _ => {
+                let bad = parts
+                    .iter()
+                    .find(|p| !p.starts_with(['B', 'b', 'S', 's']))
+                    .unwrap();
+                return Err(ParseRuleError::new(bad, "expected a 'B' or 'S' prefix"));
+            }
+        }
+
+        Ok(Rule {
+            birth: birth.unwrap(),
+            survival: survival.unwrap(),
+            states,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        fmt_counts(f, &self.birth)?;
+        write!(f, "/S")?;
+        fmt_counts(f, &self.survival)?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+/// Larger than Life rule: a neighborhood of range r, usually large,
+/// with intervals of neighbor counts for birth and survival
+/// counts never include the cell itself
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ltl {
+    /// the shape and range of the neighborhood
+    pub neighborhood: Neighborhood,
+    /// the neighbor counts that give birth to a dead cell
+    pub birth: RangeInclusive<usize>,
+    /// the neighbor counts that let a live cell survive
+    pub survival: RangeInclusive<usize>,
+    /// number of states including dead and alive
+    pub states: u8,
+}
+
+impl Ltl {
+    /// Bosco's rule, R5,C0,M1,S34..58,B34..45,NM
+    pub fn bosco() -> Self {
+        Ltl {
+            neighborhood: Neighborhood::Moore(5),
+            birth: 34..=45,
+            survival: 33..=57,
+            states: 2,
+        }
+    }
+
+    /// the equivalent outer totalistic rule
+    pub fn rule(&self) -> Rule {
+        Rule::generations(self.birth.clone(), self.survival.clone(), self.states)
+    }
+}
+
+// parse an interval of neighbor counts, "34..58"
+fn parse_interval(s: &str) -> Result<RangeInclusive<usize>, ParseRuleError> {
+    let bad = || ParseRuleError::new(s, "invalid interval");
+    let (lo, hi) = s.split_once("..").ok_or_else(bad)?;
+    let lo = lo.parse().map_err(|_| bad())?;
+    let hi = hi.parse().map_err(|_| bad())?;
+
+    Ok(lo..=hi)
+}
+
+// accepts the Golly form "R5,C0,M1,S34..58,B34..45,NM" with the parts in
+// any order, C, M and N are optional and default to C0, M0 and NM
+// M1 counts the cell itself for survival, which is shifted out on parsing
+impl FromStr for Ltl {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut range = None;
+        let mut states = 2;
+        let mut middle = false;
+        let mut birth = None;
+        let mut survival = None;
+        let mut shape = 'M';
+
+        for token in s.trim().split(',') {
+            let token = token.trim();
+            let value = token.get(1..).unwrap_or("");
+            let bad = |reason| ParseRuleError::new(token, reason);
+
+            match token.chars().next().map(|c| c.to_ascii_uppercase()) {
+                Some('R') => range = Some(value.parse().map_err(|_| bad("invalid range"))?),
+                // C0 and C1 are two-state rules as well
+                Some('C') => match value.parse::<u8>() {
+                    Ok(n) => states = n.max(2),
+                    _ => return Err(bad("invalid number of states")),
+                },
+                Some('M') => match value {
+                    "0" => middle = false,
+                    "1" => middle = true,
+                    _ => return Err(bad("expected M0 or M1")),
+                },
+                Some('S') => survival = Some(parse_interval(value)?),
+                Some('B') => birth = Some(parse_interval(value)?),
+                Some('N') => match value {
+                    "M" | "m" | "N" | "n" | "+" => {
+                        shape = value.as_bytes()[0].to_ascii_uppercase() as char
+                    }
+                    _ => return Err(bad("unsupported neighborhood")),
+                },
+                _ => return Err(bad("unknown part")),
+            }
+        }
+
+        let range = range.ok_or_else(|| ParseRuleError::new(s, "missing range"))?;
+        let birth = birth.ok_or_else(|| ParseRuleError::new(s, "missing birth interval"))?;
+        let mut survival =
+            survival.ok_or_else(|| ParseRuleError::new(s, "missing survival interval"))?;
+        if middle {
+            survival = survival.start().saturating_sub(1)..=survival.end().saturating_sub(1);
+        }
+        let neighborhood = match shape {
+            'N' => Neighborhood::VonNeumann(range),
+            '+' => Neighborhood::Cross(range),
+            _ => Neighborhood::Moore(range),
+        };
+
+        Ok(Ltl {
+            neighborhood,
+            birth,
+            survival,
+            states,
+        })
+    }
+}
+
+impl fmt::Display for Ltl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let shape = match self.neighborhood {
+            Neighborhood::Moore(_) => "M",
+            Neighborhood::VonNeumann(_) => "N",
+            Neighborhood::Cross(_) => "+",
+        };
+        write!(
+            f,
+            "R{},C{},M0,S{}..{},B{}..{},N{}",
+            self.neighborhood.range(),
+            if self.states > 2 { self.states } else { 0 },
+            self.survival.start(),
+            self.survival.end(),
+            self.birth.start(),
+            self.birth.end(),
+            shape
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let conway = Rule::conway();
+        assert_eq!("B3/S23".parse(), Ok(conway.clone()));
+        assert_eq!("s23/b3".parse(), Ok(conway.clone()));
+        assert_eq!("23/3".parse(), Ok(conway));
+
+        let rule: Rule = "B5,6,7/S4,5,12".parse().unwrap();
+        assert_eq!(rule, Rule::new([5, 6, 7], [4, 5, 12]));
+
+        let rule: Rule = "B2/S".parse().unwrap();
+        assert_eq!(rule, Rule::new([2], []));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = "B3x/S23".parse::<Rule>().unwrap_err();
+        assert_eq!(err.token(), "x");
+
+        let err = "B5,y/S4".parse::<Rule>().unwrap_err();
+        assert_eq!(err.token(), "y");
+
+        let err = "B3/23".parse::<Rule>().unwrap_err();
+        assert_eq!(err.token(), "23");
+
+        let err = "B3/B23".parse::<Rule>().unwrap_err();
+        assert_eq!(err.token(), "B23");
+
+        assert!("B3".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Rule::conway().to_string(), "B3/S23");
+        assert_eq!(Rule::new([5, 6, 7], [4, 5, 12]).to_string(), "B567/S4,5,12");
+
+        let rule = Rule::new([2, 10], []);
+        assert_eq!(rule.to_string().parse(), Ok(rule));
+
+        let rule = Rule::new([3], [12]);
+        assert_eq!(rule.to_string(), "B3/S12,");
+        assert_eq!(rule.to_string().parse(), Ok(rule));
+    }
+
+    #[test]
+    fn survival_first() {
+        assert_eq!(Rule::conway().survival_first(), "23/3");
+
+        for rule in [
+            Rule::new([3, 6], [2, 3]),
+            Rule::new([3], [12]),
+            Rule::new([5, 6, 7], [4, 5, 12]),
+            Rule::generations([2], [], 3),
+        ] {
+            assert_eq!(rule.survival_first().parse(), Ok(rule));
+        }
+        assert_eq!(Rule::generations([2], [], 3).survival_first(), "/2/3");
+    }
+
+    #[test]
+    fn generations() {
+        let brain = Rule::generations([2], [], 3);
+        assert_eq!("/2/3".parse(), Ok(brain.clone()));
+        assert_eq!("B2/S/C3".parse(), Ok(brain.clone()));
+        assert_eq!(brain.to_string(), "B2/S/C3");
+
+        let star_wars = Rule::generations([2], [3, 4, 5], 4);
+        assert_eq!("345/2/4".parse(), Ok(star_wars.clone()));
+        assert_eq!("B2/S345/C4".parse(), Ok(star_wars));
+
+        let err = "B2/S345/X".parse::<Rule>().unwrap_err();
+        assert_eq!(err.token(), "X");
+        assert!("B2/S345/C1".parse::<Rule>().is_err());
+
+        let ltl: Ltl = "R2,C5,M0,S2..4,B3..3,NM".parse().unwrap();
+        assert_eq!(ltl.rule().states, 5);
+        assert_eq!(ltl.to_string(), "R2,C5,M0,S2..4,B3..3,NM");
+    }
+
+    #[test]
+    fn ltl() {
+        let bosco: Ltl = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        assert_eq!(bosco, Ltl::bosco());
+        assert_eq!(bosco.to_string(), "R5,C0,M0,S33..57,B34..45,NM");
+        assert_eq!(bosco.to_string().parse(), Ok(bosco));
+
+        let ltl: Ltl = "R2,B3..4,S2..5,NN".parse().unwrap();
+        assert_eq!(ltl.neighborhood, Neighborhood::VonNeumann(2));
+
+        let err = "R5,C0,M1,S34-58,B34..45,NM".parse::<Ltl>().unwrap_err();
+        assert_eq!(err.token(), "34-58");
+        let err = "R5,S34..58,B34..45,NX".parse::<Ltl>().unwrap_err();
+        assert_eq!(err.token(), "NX");
+    }
+}
@@ -0,0 +1,280 @@
+//! universe for patterns that are symmetric in the extra axes
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::coord::OutOfRange;
+use crate::neighborhood::Neighborhood;
+use crate::rule::Rule;
+use crate::universe::Universe;
+use crate::vec_add;
+use crate::Vector;
+
+/// life for patterns that are symmetric in the axes after the first few,
+/// like a seed in the xy plane: mirroring any extra axis or permuting the
+/// extra axes doesn't change the evolution, so only one cell of each orbit
+/// is stored, the one with non-negative, sorted extra coordinates
+/// mutation acts on whole orbits, creating a cell creates all its images
+///
+/// unstable, this can change in any release
+pub struct Symmetric<const N: usize> {
+    cells: HashSet<Vector<N>>, // canonical representatives
+    free: usize,               // axes that aren't reduced
+    neighbors: Vec<Vector<N>>,
+    rule: Rule,
+    generation: u64,
+}
+
+fn factorial(n: usize) -> usize {
+    (1..=n).product()
+}
+
+// all distinct orderings of the values
+fn permutations(values: &[i32]) -> HashSet<Vec<i32>> {
+    if values.len() <= 1 {
+        return HashSet::from([values.to_vec()]);
+    }
+
+    let mut perms = HashSet::new();
+    for i in 0..values.len() {
+        let mut rest = values.to_vec();
+        let first = rest.remove(i);
+        for mut p in permutations(&rest) {
+            p.insert(0, first);
+            perms.insert(p);
+        }
+    }
+
+    perms
+}
+
+impl<const N: usize> Symmetric<N> {
+    /// the first `free` axes keep their coordinates, 2 for a seed in the
+    /// xy plane
+    pub fn new(free: usize) -> Self {
+        Self::with_rule(free, Rule::default(), Neighborhood::default())
+    }
+
+    /// the neighborhoods are all symmetric under the reductions
+    pub fn with_rule(free: usize, rule: Rule, neighborhood: Neighborhood) -> Self {
+        assert!(free <= N, "more free axes than dimensions");
+        assert!(
+            rule.states == 2,
+            "symmetric life only supports two-state rules"
+        );
+
+        Symmetric {
+            cells: HashSet::new(),
+            free,
+            neighbors: neighborhood.offsets(),
+            rule,
+            generation: 0,
+        }
+    }
+
+    /// the representative of the position's orbit
+    pub fn canonical(&self, mut pos: Vector<N>) -> Vector<N> {
+        let extra = &mut pos[self.free..];
+        for x in extra.iter_mut() {
+            *x = x.checked_abs().expect("the mirror image is out of range");
+        }
+        extra.sort_unstable();
+
+        pos
+    }
+
+    /// number of cells in the orbit of a canonical position
+    pub fn orbit_size(&self, pos: &Vector<N>) -> usize {
+        let extra = &pos[self.free..];
+        let signs = 1 << extra.iter().filter(|&&x| x != 0).count();
+
+        // the extra coordinates are sorted, so equal ones are adjacent
+        let repeats: usize = extra
+            .chunk_by(|a, b| a == b)
+            .map(|run| factorial(run.len()))
+            .product();
+
+        signs * factorial(extra.len()) / repeats
+    }
+
+    /// all the cells of the orbit of a canonical position
+    pub fn orbit(&self, pos: &Vector<N>) -> Vec<Vector<N>> {
+        let mut cells = Vec::new();
+
+        for perm in permutations(&pos[self.free..]) {
+            let nonzero: Vec<_> = (0..perm.len()).filter(|&i| perm[i] != 0).collect();
+
+            for signs in 0..1 << nonzero.len() {
+                let mut c = *pos;
+                c[self.free..].copy_from_slice(&perm);
+                for (bit, &i) in nonzero.iter().enumerate() {
+                    if signs >> bit & 1 == 1 {
+                        c[self.free + i] = -c[self.free + i];
+                    }
+                }
+                cells.push(c);
+            }
+        }
+
+        cells
+    }
+
+    /// the full set of live cells
+    pub fn expand(&self) -> HashSet<Vector<N>> {
+        self.cells.iter().flat_map(|c| self.orbit(c)).collect()
+    }
+
+    /// perform a life cycle
+    /// a canonical cell stands for its whole orbit, so it adds its orbit
+    /// size to the canonical image of each of its neighbors, dividing that
+    /// by the neighbor's orbit size gives the count of any cell of it
+    /// panics if the pattern reaches the edge of the coordinate range
+    pub fn cycle(&mut self) {
+        if let Err(e) = self.try_cycle() {
+            panic!("{}", e);
+        }
+    }
+
+    /// perform a life cycle, or leave the universe unchanged and return
+    /// an error if the pattern reaches the edge of the coordinate range
+    pub fn try_cycle(&mut self) -> Result<(), OutOfRange> {
+        // the neighbors and their mirror images must fit, the extra
+        // coordinates are non-negative so only the top of the range matters
+        let reach = self.neighbors.iter().flatten().map(|x| x.abs()).max();
+        let inside = i32::MIN + reach.unwrap_or(0)..=i32::MAX - reach.unwrap_or(0);
+        if let Some(c) = self
+            .cells
+            .iter()
+            .find(|c| !c.iter().all(|x| inside.contains(x)))
+        {
+            return Err(OutOfRange::new(c));
+        }
+
+        let mut weights: HashMap<Vector<N>, usize> = HashMap::new();
+
+        for c in self.cells.iter() {
+            let w = self.orbit_size(c);
+            for d in self.neighbors.iter() {
+                *weights.entry(self.canonical(vec_add(c, d))).or_insert(0) += w;
+            }
+        }
+
+        let mut next: HashSet<_> = weights
+            .iter()
+            .filter(|(pos, &w)| {
+                let n = w / self.orbit_size(pos);
+                if self.cells.contains(*pos) {
+                    self.rule.survives(n)
+                } else {
+                    self.rule.born(n)
+                }
+            })
+            .map(|(&pos, _)| pos)
+            .collect();
+
+        if self.rule.survives(0) {
+            let lonely = self.cells.iter().filter(|c| !weights.contains_key(*c));
+            next.extend(lonely);
+        }
+
+        self.cells = next;
+        self.generation += 1;
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> Universe<N> for Symmetric<N> {
+    fn get(&self, pos: &Vector<N>) -> bool {
+        self.cells.contains(&self.canonical(*pos))
+    }
+
+    fn set(&mut self, pos: Vector<N>, alive: bool) {
+        let pos = self.canonical(pos);
+        if alive {
+            self.cells.insert(pos);
+        } else {
+            self.cells.remove(&pos);
+        }
+    }
+
+    fn cycle(&mut self) {
+        Symmetric::cycle(self)
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> usize {
+        self.cells.iter().map(|c| self.orbit_size(c)).sum()
+    }
+
+    fn cells(&self) -> Box<dyn Iterator<Item = Vector<N>> + '_> {
+        Box::new(self.cells.iter().flat_map(|c| self.orbit(c)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Life;
+
+    #[test]
+    fn orbits() {
+        let s = Symmetric::<5>::new(2);
+        assert_eq!(s.canonical([1, -2, -3, 0, 3]), [1, -2, 0, 3, 3]);
+
+        for pos in [
+            [0, 0, 0, 0, 0],
+            [1, 2, 0, 1, 1],
+            [0, 0, 1, 2, 3],
+            [5, 5, 0, 0, 4],
+        ] {
+            let orbit = s.orbit(&pos);
+            assert_eq!(orbit.len(), s.orbit_size(&pos));
+            assert!(orbit.iter().all(|&c| s.canonical(c) == pos));
+        }
+    }
+
+    #[test]
+    fn matches_life() {
+        let mut life = Life::<5>::new();
+        let mut symmetric = Symmetric::<5>::new(2);
+        for pos in [
+            [0, 1, 0, 0, 0],
+            [0, 0, 0, 0, 0],
+            [0, -1, 0, 0, 0],
+            [1, 1, 0, 0, 0],
+        ] {
+            life.create(pos);
+            symmetric.create(pos);
+        }
+
+        for _ in 0..4 {
+            life.cycle();
+            symmetric.cycle();
+            assert_eq!(symmetric.expand(), life.cells);
+            assert_eq!(Universe::population(&symmetric), life.cells.len());
+        }
+    }
+
+    #[test]
+    fn coordinate_range() {
+        // a blinker at the top of the range of an extra axis
+        let mut symmetric = Symmetric::<3>::new(2);
+        for x in -1..=1 {
+            symmetric.create([x, 0, i32::MAX - 1]);
+        }
+        assert!(symmetric.try_cycle().is_ok());
+
+        let mut symmetric = Symmetric::<3>::new(2);
+        for x in -1..=1 {
+            symmetric.create([0, x, i32::MAX]);
+        }
+        let before = symmetric.cells.clone();
+        let err = symmetric.try_cycle().unwrap_err();
+        assert_eq!(err.position()[2], i32::MAX as i64);
+        assert_eq!(symmetric.cells, before);
+    }
+}
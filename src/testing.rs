@@ -0,0 +1,98 @@
+// helpers shared by the tests of several modules
+
+use std::collections::HashSet;
+
+use crate::rule::Rule;
+use crate::universe::Universe;
+use crate::Life;
+use crate::Vector;
+
+// a linear congruential generator, enough for deterministic soups
+pub struct Lcg(u32);
+
+impl Lcg {
+    pub fn new(seed: u32) -> Self {
+        Lcg(seed)
+    }
+
+    // the next 15 bits
+    pub fn next(&mut self) -> u32 {
+        self.0 = self.0.wrapping_mul(1103515245).wrapping_add(12345);
+        (self.0 >> 16) & 0x7fff
+    }
+}
+
+// a deterministic soup filling about half of the box 0..sizes[a] along
+// each axis, the first axis changes fastest
+pub fn soup<const N: usize>(sizes: [i32; N], seed: u32) -> Vec<Vector<N>> {
+    let mut lcg = Lcg::new(seed);
+    let mut cells = Vec::new();
+
+    for t in 0..sizes.iter().product() {
+        if lcg.next() & 1 == 1 {
+            let mut c = [0; N];
+            let mut t = t;
+            for (x, size) in c.iter_mut().zip(sizes) {
+                *x = t % size;
+                t /= size;
+            }
+            cells.push(c);
+        }
+    }
+
+    cells
+}
+
+// count deterministic random positions in the box -r..=r, duplicates are
+// merged so there can be fewer
+pub fn points<const N: usize>(count: usize, r: i32, seed: u32) -> HashSet<Vector<N>> {
+    let mut lcg = Lcg::new(seed);
+    let side = 2 * r as u32 + 1;
+
+    (0..count)
+        .map(|_| [0; N].map(|_| (lcg.next() % side) as i32 - r))
+        .collect()
+}
+
+// what compare needs from a backend, so the ones that don't implement
+// Universe can be compared too
+pub trait Backend<const N: usize> {
+    fn create(&mut self, pos: Vector<N>);
+    fn cycle(&mut self);
+    fn live(&self) -> HashSet<Vector<N>>;
+}
+
+impl<const N: usize, U: Universe<N>> Backend<N> for U {
+    fn create(&mut self, pos: Vector<N>) {
+        Universe::create(self, pos);
+    }
+
+    fn cycle(&mut self) {
+        Universe::cycle(self);
+    }
+
+    fn live(&self) -> HashSet<Vector<N>> {
+        self.cells().collect()
+    }
+}
+
+// run a backend next to Life from the same seed and check that they agree
+// after every generation
+pub fn compare<const N: usize, B: Backend<N>>(
+    backend: &mut B,
+    rule: Rule,
+    seed: &[Vector<N>],
+    generations: usize,
+) {
+    let mut life = Life::<N>::with_rule(rule);
+    for &c in seed {
+        backend.create(c);
+        life.create(c);
+    }
+
+    for _ in 0..generations {
+        backend.cycle();
+        life.cycle();
+        assert_eq!(backend.live(), life.cells);
+    }
+}
@@ -0,0 +1,341 @@
+//! sparse universe hashing tiles of cells
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::coord::OutOfRange;
+use crate::neighborhood::Neighborhood;
+use crate::rule::Rule;
+use crate::universe::Universe;
+use crate::Vector;
+
+// cells along each axis of a tile, small enough that a tile stays a few
+// kilobytes in high dimensions
+const fn edge(n: usize) -> i32 {
+    if n <= 3 {
+        8
+    } else {
+        4
+    }
+}
+
+/// sparse infinite life that hashes tiles of edge^N cells instead of
+/// single cells, each tile holds a bitmap
+/// a tile can only change if a tile next to it changed in the previous
+/// generation, so the others are skipped
+/// supports two-state rules with neighborhoods of range up to the edge
+/// a cycle stops with an error before the pattern grows into a tile whose
+/// cells don't all fit into i32 coordinates
+///
+/// unstable, this can change in any release
+pub struct Tiled<const N: usize> {
+    tiles: HashMap<Vector<N>, Vec<u64>>, // non-empty tiles by tile coordinates
+    changed: HashSet<Vector<N>>,         // tiles that changed last generation
+    neighbors: Vec<Vector<N>>,
+    rule: Rule,
+    generation: u64,
+}
+
+impl<const N: usize> Tiled<N> {
+    const EDGE: i32 = edge(N);
+    const SHIFT: u32 = Self::EDGE.trailing_zeros();
+    const CELLS: usize = (Self::EDGE as usize).pow(N as u32);
+
+    /// an empty universe playing the original game
+    pub fn new() -> Self {
+        Self::with_rule(Rule::default(), Neighborhood::default())
+    }
+
+    /// an empty universe with another rule and neighborhood
+    /// panics if the rule or the neighborhood isn't supported
+    pub fn with_rule(rule: Rule, neighborhood: Neighborhood) -> Self {
+        assert!(rule.states == 2, "tiles only support two-state rules");
+        assert!(!rule.born(0), "tiles don't support B0 rules");
+        assert!(
+            neighborhood.range() as i32 <= Self::EDGE,
+            "the neighborhood doesn't fit into the adjacent tiles"
+        );
+
+        Tiled {
+            tiles: HashMap::new(),
+            changed: HashSet::new(),
+            neighbors: neighborhood.offsets(),
+            rule,
+            generation: 0,
+        }
+    }
+
+    // the tile holding a position and the index of the cell in it
+    fn locate(pos: &Vector<N>) -> (Vector<N>, usize) {
+        let tile = pos.map(|x| x >> Self::SHIFT);
+        let index = pos.iter().rev().fold(0, |i, &x| {
+            (i << Self::SHIFT) | (x & (Self::EDGE - 1)) as usize
+        });
+
+        (tile, index)
+    }
+
+    // the local coordinates of a cell index
+    fn local(index: usize) -> Vector<N> {
+        let mut pos = [0; N];
+
+        for (a, x) in pos.iter_mut().enumerate() {
+            *x = (index >> (a as u32 * Self::SHIFT)) as i32 & (Self::EDGE - 1);
+        }
+
+        pos
+    }
+
+    // true if every cell of the tile has i32 coordinates
+    fn inside(tile: &Vector<N>) -> bool {
+        let range = i32::MIN >> Self::SHIFT..=i32::MAX >> Self::SHIFT;
+        tile.iter().all(|x| range.contains(x))
+    }
+
+    // the position of the first cell of a tile inside the range
+    fn corner(tile: &Vector<N>) -> Vector<N> {
+        tile.map(|x| x.checked_mul(Self::EDGE).expect("tile outside the range"))
+    }
+
+    // the tile coordinates within one tile of the given ones
+    fn around(tile: &Vector<N>) -> impl Iterator<Item = Vector<N>> + '_ {
+        (0..3usize.pow(N as u32)).map(move |mut t| {
+            let mut n = *tile;
+            for x in n.iter_mut() {
+                *x += (t % 3) as i32 - 1;
+                t /= 3;
+            }
+            n
+        })
+    }
+
+    /// perform a life cycle
+    /// panics if the pattern reaches the edge of the coordinate range
+    pub fn cycle(&mut self) {
+        if let Err(e) = self.try_cycle() {
+            panic!("{}", e);
+        }
+    }
+
+    /// perform a life cycle, or leave the universe unchanged and return
+    /// an error if the pattern reaches the edge of the coordinate range
+    pub fn try_cycle(&mut self) -> Result<(), OutOfRange> {
+        // the tiles next to a changed one can come alive, so they must be
+        // inside the range too
+        if let Some(tile) = self
+            .changed
+            .iter()
+            .find(|t| Self::around(t).any(|n| !Self::inside(&n)))
+        {
+            return Err(OutOfRange::new(&Self::corner(tile)));
+        }
+
+        // the tiles that can change, and the tiles whose cells can be
+        // their neighbors
+        let active: HashSet<_> = self.changed.iter().flat_map(Self::around).collect();
+        let sources: HashSet<_> = active
+            .iter()
+            .flat_map(Self::around)
+            .filter(|t| self.tiles.contains_key(t))
+            .collect();
+
+        let active: Vec<_> = active.into_iter().collect();
+        let index: HashMap<_, _> = active.iter().enumerate().map(|(i, &t)| (t, i)).collect();
+        let mut counts = vec![vec![0u32; Self::CELLS]; active.len()];
+
+        // each live cell adds one to the cells it is a neighbor of,
+        // which for offset d is the cell at c - d
+        for s in sources {
+            let targets: Vec<_> = Self::around(&s).map(|t| index.get(&t).copied()).collect();
+            let bits = &self.tiles[&s];
+
+            for (w, &word) in bits.iter().enumerate() {
+                let mut word = word;
+                while word != 0 {
+                    let l = Self::local(w * 64 + word.trailing_zeros() as usize);
+                    word &= word - 1;
+
+                    for d in self.neighbors.iter() {
+                        let mut delta = 0;
+                        let mut q = 0;
+                        for a in (0..N).rev() {
+                            let p = l[a] - d[a];
+                            let t = p >> Self::SHIFT;
+                            delta = delta * 3 + (t + 1) as usize;
+                            q = (q << Self::SHIFT) | (p & (Self::EDGE - 1)) as usize;
+                        }
+                        if let Some(i) = targets[delta] {
+                            counts[i][q] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut changed = HashSet::new();
+        for (tile, counts) in active.into_iter().zip(counts) {
+            let old = self.tiles.get(&tile);
+            let mut bits = vec![0u64; Self::CELLS.div_ceil(64)];
+
+            for (i, &n) in counts.iter().enumerate() {
+                let alive = old.is_some_and(|b| b[i / 64] >> (i % 64) & 1 == 1);
+                let n = n as usize;
+                if (alive && self.rule.survives(n)) || (!alive && self.rule.born(n)) {
+                    bits[i / 64] |= 1 << (i % 64);
+                }
+            }
+
+            if old.map_or(bits.iter().any(|&w| w != 0), |b| *b != bits) {
+                changed.insert(tile);
+                if bits.iter().all(|&w| w == 0) {
+                    self.tiles.remove(&tile);
+                } else {
+                    self.tiles.insert(tile, bits);
+                }
+            }
+        }
+
+        self.changed = changed;
+        self.generation += 1;
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for Tiled<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Universe<N> for Tiled<N> {
+    fn get(&self, pos: &Vector<N>) -> bool {
+        let (tile, i) = Self::locate(pos);
+        self.tiles
+            .get(&tile)
+            .is_some_and(|b| b[i / 64] >> (i % 64) & 1 == 1)
+    }
+
+    fn set(&mut self, pos: Vector<N>, alive: bool) {
+        let (tile, i) = Self::locate(&pos);
+        let words = Self::CELLS.div_ceil(64);
+        let bits = self.tiles.entry(tile).or_insert_with(|| vec![0; words]);
+
+        if alive {
+            bits[i / 64] |= 1 << (i % 64);
+        } else {
+            bits[i / 64] &= !(1 << (i % 64));
+        }
+        if bits.iter().all(|&w| w == 0) {
+            self.tiles.remove(&tile);
+        }
+
+        self.changed.insert(tile);
+    }
+
+    fn cycle(&mut self) {
+        Tiled::cycle(self)
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn population(&self) -> usize {
+        self.tiles
+            .values()
+            .flatten()
+            .map(|w| w.count_ones() as usize)
+            .sum()
+    }
+
+    fn cells(&self) -> Box<dyn Iterator<Item = Vector<N>> + '_> {
+        let cells = self.tiles.iter().flat_map(|(tile, bits)| {
+            (0..Self::CELLS)
+                .filter(|i| bits[i / 64] >> (i % 64) & 1 == 1)
+                .map(move |i| {
+                    let mut pos = Self::local(i);
+                    for (x, c) in pos.iter_mut().zip(Self::corner(tile)) {
+                        *x += c;
+                    }
+                    pos
+                })
+        });
+
+        Box::new(cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn compare<const N: usize>(rule: Rule, seed: &[Vector<N>], generations: usize) {
+        let mut tiled = Tiled::<N>::with_rule(rule.clone(), Neighborhood::default());
+        testing::compare(&mut tiled, rule, seed, generations);
+    }
+
+    #[test]
+    fn matches_life() {
+        // the R-pentomino
+        let r = [[1, 0], [2, 0], [0, 1], [1, 1], [1, 2]];
+        compare::<2>(Rule::default(), &r, 200);
+
+        let seed = [
+            [0, 0, 0],
+            [1, 0, 0],
+            [0, 1, 0],
+            [0, 0, 1],
+            [1, 1, 1],
+            [-1, 1, 0],
+        ];
+        compare::<3>("B5/S45".parse().unwrap(), &seed, 10);
+
+        let seed = [[0, 1, 0, 0], [0, 0, 0, 0], [0, -1, 0, 0]];
+        compare::<4>(Rule::default(), &seed, 4);
+    }
+
+    #[test]
+    fn skips_stable_tiles() {
+        let mut tiled = Tiled::<2>::new();
+
+        // a block far away from a blinker
+        for c in [[100, 100], [100, 101], [101, 100], [101, 101]] {
+            tiled.create(c);
+        }
+        for c in [[0, -1], [0, 0], [0, 1]] {
+            tiled.create(c);
+        }
+
+        tiled.cycle();
+        tiled.cycle();
+
+        let (block, _) = Tiled::<2>::locate(&[100, 100]);
+        assert!(!tiled.changed.contains(&block));
+        assert!(!tiled.changed.is_empty());
+        assert_eq!(tiled.population(), 7);
+        assert!(tiled.get(&[101, 101]));
+    }
+
+    #[test]
+    fn coordinate_range() {
+        // a glider running into the edge of the i32 range stops with an
+        // error instead of wrapping around
+        let mut tiled = Tiled::<2>::new();
+        for [x, y] in [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]] {
+            tiled.create([i32::MAX - 40 + x, i32::MAX - 40 + y]);
+        }
+
+        let err = loop {
+            let before: HashSet<_> = tiled.cells().collect();
+            if let Err(e) = tiled.try_cycle() {
+                assert_eq!(tiled.cells().collect::<HashSet<_>>(), before);
+                break e;
+            }
+        };
+        assert!(err.position().iter().all(|&x| x > i32::MAX as i64 - 40));
+        assert!(tiled.cells().flatten().all(|x| x > i32::MAX - 40));
+        assert_eq!(tiled.population(), 5);
+    }
+}
@@ -0,0 +1,363 @@
+//! how the axes of a universe end: unbounded, bounded, wrapping or
+//! folded
+
+use crate::coord::Coord;
+use crate::vec_add;
+use crate::vec_sub;
+use crate::Vector;
+
+/// behaviour of a single axis
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// coordinates can grow without limit
+    Unbounded,
+    /// coordinates are in 0..size and wrap around
+    Wrap(i32),
+    /// coordinates are in 0..size, cells outside are always dead
+    Dead(i32),
+    /// coordinates are in 0..size, the cells outside mirror the inside,
+    /// -1 is the same cell as 0 and size is the same as size - 1
+    Reflect(i32),
+    /// coordinates are in 0..size and wrap around, mirroring the given
+    /// other axis on every crossing of the seam, as on a Klein bottle
+    Twist(i32, usize),
+}
+
+impl Boundary {
+    /// None for unbounded axes
+    pub fn size(&self) -> Option<i32> {
+        match *self {
+            Boundary::Unbounded => None,
+            Boundary::Wrap(size)
+            | Boundary::Dead(size)
+            | Boundary::Reflect(size)
+            | Boundary::Twist(size, _) => Some(size),
+        }
+    }
+
+    fn normalize(&self, x: i64) -> Option<i64> {
+        let size = self.size().map_or(0, |s| s as i64);
+        match *self {
+            Boundary::Unbounded => Some(x),
+            Boundary::Wrap(_) | Boundary::Twist(..) => Some(x.rem_euclid(size)),
+            Boundary::Dead(_) => (0..size).contains(&x).then_some(x),
+            Boundary::Reflect(_) => {
+                let x = x.rem_euclid(2 * size);
+                Some(if x < size { x } else { 2 * size - 1 - x })
+            }
+        }
+    }
+}
+
+/// shape of the universe, one boundary for each axis
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Topology<const N: usize> {
+    axes: [Boundary; N],
+}
+
+impl<const N: usize> Topology<N> {
+    /// a topology with the given boundary for each axis
+    /// panics if a size isn't positive or a twisted axis doesn't mirror
+    /// another bounded axis
+    pub fn new(axes: [Boundary; N]) -> Self {
+        for (i, axis) in axes.iter().enumerate() {
+            if let Some(size) = axis.size() {
+                assert!(size > 0, "axis size must be positive");
+            }
+            if let Boundary::Twist(_, other) = *axis {
+                assert!(
+                    other != i && other < N && axes[other].size().is_some(),
+                    "a twisted axis must mirror another bounded axis"
+                );
+            }
+        }
+
+        Topology { axes }
+    }
+
+    /// the infinite universe
+    pub fn unbounded() -> Self {
+        Self::new([Boundary::Unbounded; N])
+    }
+
+    /// a torus with the given period along each axis
+    pub fn torus(sizes: [i32; N]) -> Self {
+        Self::new(sizes.map(Boundary::Wrap))
+    }
+
+    /// a box with the given size along each axis and dead cells outside
+    pub fn bounded(sizes: [i32; N]) -> Self {
+        Self::new(sizes.map(Boundary::Dead))
+    }
+}
+
+impl Topology<2> {
+    /// wrapping around x mirrors y, wrapping around y is a plain torus seam
+    pub fn klein_bottle(width: i32, height: i32) -> Self {
+        Self::new([Boundary::Twist(width, 1), Boundary::Wrap(height)])
+    }
+
+    /// the cross-surface, wrapping around either axis mirrors the other
+    pub fn projective_plane(width: i32, height: i32) -> Self {
+        Self::new([Boundary::Twist(width, 1), Boundary::Twist(height, 0)])
+    }
+}
+
+impl<const N: usize> Topology<N> {
+    /// the boundary of each axis
+    pub fn axes(&self) -> &[Boundary; N] {
+        &self.axes
+    }
+
+    /// true if no axis has a boundary
+    pub fn is_unbounded(&self) -> bool {
+        self.axes.iter().all(|a| *a == Boundary::Unbounded)
+    }
+
+    /// true if every coordinate inside the bounded axes fits into C
+    pub fn fits<C: Coord>(&self) -> bool {
+        self.axes
+            .iter()
+            .filter_map(Boundary::size)
+            .all(|size| C::from_i64(size as i64 - 1).is_some())
+    }
+
+    /// map a position to its canonical coordinates in the universe,
+    /// None if it is outside and always dead
+    pub fn normalize<C: Coord>(&self, pos: Vector<N, C>) -> Option<Vector<N, C>> {
+        if self.is_unbounded() {
+            return Some(pos);
+        }
+
+        self.normalize_wide(pos.map(C::to_i64))
+    }
+
+    // normalize coordinates that may be outside the range of C, the
+    // bounded axes bring them back into it
+    // panics if an unbounded coordinate doesn't fit
+    fn normalize_wide<C: Coord>(&self, mut pos: [i64; N]) -> Option<Vector<N, C>> {
+        for (i, axis) in self.axes.iter().enumerate() {
+            // an odd number of crossings mirrors the other axis, mirroring
+            // commutes with normalizing it so the order doesn't matter
+            if let Boundary::Twist(size, other) = *axis {
+                if pos[i].div_euclid(size as i64) % 2 != 0 {
+                    let other_size = self.axes[other].size().unwrap() as i64;
+                    pos[other] = other_size - 1 - pos[other];
+                }
+            }
+            pos[i] = axis.normalize(pos[i])?;
+        }
+
+        let mut res = [C::default(); N];
+        for (r, x) in res.iter_mut().zip(pos) {
+            *r = C::from_i64(x).expect("coordinate overflow");
+        }
+
+        Some(res)
+    }
+
+    /// the positions p with add(p, d) == pos, the cells that have pos as
+    /// their neighbor at offset d
+    /// with wrapping and dead axes there is at most one, pos - d, but
+    /// reflecting and twisted axes can fold several cells onto the same
+    /// neighbor or none
+    pub fn sources<C: Coord>(
+        &self,
+        pos: &Vector<N, C>,
+        d: &Vector<N, C>,
+    ) -> impl Iterator<Item = Vector<N, C>> {
+        let folds = self
+            .axes
+            .iter()
+            .any(|a| matches!(a, Boundary::Reflect(_) | Boundary::Twist(..)));
+
+        let (single, folded) = if folds {
+            (None, self.folded_sources(pos, d))
+        } else {
+            (self.normalize(vec_sub(pos, d)), Vec::new())
+        };
+
+        single.into_iter().chain(folded)
+    }
+
+    // sources on reflecting and twisted axes, each coordinate can only take
+    // a few values so every combination of them is tried with add
+    fn folded_sources<C: Coord>(&self, pos: &Vector<N, C>, d: &Vector<N, C>) -> Vec<Vector<N, C>> {
+        let choices: Vec<Vec<C>> = (0..N)
+            .map(|a| self.source_coords(a, pos[a].to_i64(), d[a].to_i64()))
+            .collect();
+        if choices.iter().any(Vec::is_empty) {
+            return Vec::new();
+        }
+
+        let mut sources = Vec::new();
+        let mut index = [0; N];
+        loop {
+            let mut p = [C::default(); N];
+            for (a, x) in p.iter_mut().enumerate() {
+                *x = choices[a][index[a]];
+            }
+            if self.add(&p, d).as_ref() == Some(pos) {
+                sources.push(p);
+            }
+
+            // the next combination, counting with one digit per axis
+            let Some(a) = (0..N).find(|&a| index[a] + 1 < choices[a].len()) else {
+                return sources;
+            };
+            index[a] += 1;
+            index[..a].fill(0);
+        }
+    }
+
+    // the values of p[a] that can bring p[a] + d to x, or to its mirror
+    // image if a twisted axis can flip this one
+    fn source_coords<C: Coord>(&self, a: usize, x: i64, d: i64) -> Vec<C> {
+        let axis = self.axes[a];
+        let Some(size) = axis.size().map(i64::from) else {
+            return C::from_i64(x - d).into_iter().collect();
+        };
+
+        let mirrored = self
+            .axes
+            .iter()
+            .any(|b| matches!(*b, Boundary::Twist(_, other) if other == a));
+        let targets = if mirrored {
+            vec![x, size - 1 - x]
+        } else {
+            vec![x]
+        };
+
+        let mut values = Vec::new();
+        for t in targets {
+            match axis {
+                Boundary::Dead(_) => values.push(t - d),
+                Boundary::Reflect(_) => {
+                    values.push((t - d).rem_euclid(2 * size));
+                    values.push((2 * size - 1 - t - d).rem_euclid(2 * size));
+                }
+                _ => values.push((t - d).rem_euclid(size)),
+            }
+        }
+        values.retain(|v| (0..size).contains(v));
+        values.sort_unstable();
+        values.dedup();
+
+        values.into_iter().filter_map(C::from_i64).collect()
+    }
+
+    /// the position at offset d from pos
+    pub fn add<C: Coord>(&self, pos: &Vector<N, C>, d: &Vector<N, C>) -> Option<Vector<N, C>> {
+        if self.is_unbounded() {
+            return Some(vec_add(pos, d));
+        }
+
+        let mut sum = [0i64; N];
+        for (a, s) in sum.iter_mut().enumerate() {
+            *s = pos[a].to_i64() + d[a].to_i64();
+        }
+
+        self.normalize_wide(sum)
+    }
+}
+
+impl<const N: usize> Default for Topology<N> {
+    fn default() -> Self {
+        Self::unbounded()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap() {
+        let t = Topology::new([Boundary::Wrap(5), Boundary::Unbounded, Boundary::Wrap(2)]);
+        assert_eq!(t.normalize([-1, -1, 3]), Some([4, -1, 1]));
+        assert_eq!(t.add(&[4, 7, 1], &[1, 1, 1]), Some([0, 8, 0]));
+        assert!(!t.is_unbounded());
+        assert!(Topology::<3>::unbounded().is_unbounded());
+    }
+
+    #[test]
+    fn mixed() {
+        // a cylinder, wrapping on x, dead on y and unbounded on z
+        let t = Topology::new([Boundary::Wrap(4), Boundary::Dead(3), Boundary::Unbounded]);
+        assert_eq!(t.normalize([4, 2, -9]), Some([0, 2, -9]));
+        assert_eq!(t.normalize([4, 3, -9]), None);
+        assert_eq!(t.normalize([0, -1, 0]), None);
+
+        let t = Topology::new([Boundary::Reflect(3)]);
+        let xs: Vec<_> = (-4..8).map(|x| t.normalize([x]).unwrap()[0]).collect();
+        assert_eq!(xs, [2, 2, 1, 0, 0, 1, 2, 2, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn twisted() {
+        let klein = Topology::klein_bottle(4, 3);
+        assert_eq!(klein.normalize([4, 0]), Some([0, 2]));
+        assert_eq!(klein.normalize([-1, 0]), Some([3, 2]));
+        assert_eq!(klein.normalize([8, 0]), Some([0, 0]));
+        assert_eq!(klein.normalize([1, 3]), Some([1, 0]));
+
+        let plane = Topology::projective_plane(4, 3);
+        assert_eq!(plane.normalize([4, 0]), Some([0, 2]));
+        assert_eq!(plane.normalize([0, 3]), Some([3, 0]));
+        assert_eq!(plane.normalize([1, -1]), Some([2, 2]));
+    }
+
+    #[test]
+    fn sources() {
+        // every position p in the universe whose neighbor at d is pos
+        fn brute<const N: usize>(
+            t: &Topology<N>,
+            pos: Vector<N>,
+            d: Vector<N>,
+            sizes: [i32; N],
+        ) -> Vec<Vector<N>> {
+            let mut found = Vec::new();
+            for i in 0..sizes.iter().product() {
+                let mut p = [0; N];
+                let mut i = i;
+                for (x, size) in p.iter_mut().zip(sizes) {
+                    *x = i % size;
+                    i /= size;
+                }
+                if t.add(&p, &d) == Some(pos) {
+                    found.push(p);
+                }
+            }
+            found.sort_unstable();
+            found
+        }
+
+        let topologies = [
+            Topology::new([Boundary::Reflect(5), Boundary::Dead(4)]),
+            Topology::new([Boundary::Reflect(3), Boundary::Wrap(4)]),
+            Topology::klein_bottle(6, 5),
+            Topology::projective_plane(4, 3),
+            Topology::new([Boundary::Twist(4, 1), Boundary::Reflect(3)]),
+        ];
+        for t in topologies {
+            let sizes = t.axes().map(|a| a.size().unwrap());
+            for d in [[-1, 0], [1, 1], [2, -1], [-7, 3], [0, 0]] {
+                for x in 0..sizes[0] {
+                    for y in 0..sizes[1] {
+                        let mut sources: Vec<_> = t.sources(&[x, y], &d).collect();
+                        sources.sort_unstable();
+                        assert_eq!(sources, brute(&t, [x, y], d, sizes), "{:?} {:?}", t, d);
+                    }
+                }
+            }
+        }
+
+        let t = Topology::new([Boundary::Wrap(5), Boundary::Unbounded]);
+        assert_eq!(t.sources(&[0, 3], &[1, 1]).collect::<Vec<_>>(), [[4, 2]]);
+        let t = Topology::new([Boundary::Reflect(5), Boundary::Unbounded]);
+        assert_eq!(
+            t.sources(&[0, 3], &[-1, 1]).collect::<Vec<_>>(),
+            [[0, 2], [1, 2]]
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! the interface shared by every backend
+
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+use crate::coord::Coord;
+use crate::Vector;
+
+/// smallest box holding all live cells, both corners are inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundingBox<const N: usize, C = i32> {
+    /// the smallest coordinates along each axis
+    pub min: Vector<N, C>,
+    /// the largest coordinates along each axis
+    pub max: Vector<N, C>,
+}
+
+impl<const N: usize, C: Coord> BoundingBox<N, C> {
+    /// cells along each axis
+    pub fn size(&self) -> [i64; N] {
+        let mut size = [0; N];
+
+        for (a, s) in size.iter_mut().enumerate() {
+            *s = self.max[a].to_i64() - self.min[a].to_i64() + 1;
+        }
+
+        size
+    }
+
+    /// true if the position is inside the box
+    pub fn contains(&self, pos: &Vector<N, C>) -> bool {
+        (0..N).all(|a| self.min[a] <= pos[a] && pos[a] <= self.max[a])
+    }
+}
+
+/// population and extent of a universe at some generation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Summary<const N: usize, C = i32> {
+    /// number of cycles since the universe was created
+    pub generation: u64,
+    /// number of live cells
+    pub population: usize,
+    /// the box around the live cells, None if there are none
+    pub bounding_box: Option<BoundingBox<N, C>>,
+}
+
+/// the live cells of a universe at some generation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot<const N: usize, C: Coord = i32> {
+    /// number of cycles since the universe was created
+    pub generation: u64,
+    /// the live cells
+    pub cells: HashSet<Vector<N, C>>,
+}
+
+/// iterator over the generations of a universe, made by Universe::evolve
+pub struct Evolve<'a, const N: usize, C, U, F> {
+    universe: &'a mut U,
+    map: F,
+    started: bool,
+    coord: PhantomData<C>,
+}
+
+impl<const N: usize, C, U, F, T> Iterator for Evolve<'_, N, C, U, F>
+where
+    C: Coord,
+    U: Universe<N, C>,
+    F: FnMut(&U) -> T,
+{
+    type Item = T;
+
+    // the first item is the generation the universe was at, it is only
+    // advanced when the next one is asked for
+    fn next(&mut self) -> Option<T> {
+        if self.started {
+            self.universe.cycle();
+        }
+        self.started = true;
+
+        Some((self.map)(self.universe))
+    }
+}
+
+/// common interface of the storage backends, so rules, analysis and
+/// file formats can be written once
+/// C is the coordinate type, the backends that don't take one use i32
+pub trait Universe<const N: usize, C: Coord = i32> {
+    /// return true if there is a live cell at the position
+    fn get(&self, pos: &Vector<N, C>) -> bool;
+
+    /// make the cell at the position alive or dead
+    fn set(&mut self, pos: Vector<N, C>, alive: bool);
+
+    /// create a live cell at the position
+    fn create(&mut self, pos: Vector<N, C>) {
+        self.set(pos, true);
+    }
+
+    /// kill the cell at the position
+    fn kill(&mut self, pos: Vector<N, C>) {
+        self.set(pos, false);
+    }
+
+    /// perform a life cycle
+    fn cycle(&mut self);
+
+    /// number of cycles since the universe was created
+    fn generation(&self) -> u64;
+
+    /// perform k life cycles
+    fn step_n(&mut self, k: u64) {
+        for _ in 0..k {
+            self.cycle();
+        }
+    }
+
+    /// an endless iterator of map applied to each generation, starting
+    /// with the current one, the universe is left at the generation of
+    /// the last item taken
+    fn evolve<T, F>(&mut self, map: F) -> Evolve<'_, N, C, Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&Self) -> T,
+    {
+        Evolve {
+            universe: self,
+            map,
+            started: false,
+            coord: PhantomData,
+        }
+    }
+
+    /// the population and bounding box of each generation
+    fn summaries(&mut self) -> impl Iterator<Item = Summary<N, C>> + '_
+    where
+        Self: Sized,
+    {
+        self.evolve(|u| Summary {
+            generation: u.generation(),
+            population: u.population(),
+            bounding_box: u.bounding_box(),
+        })
+    }
+
+    /// the live cells of each generation
+    fn snapshots(&mut self) -> impl Iterator<Item = Snapshot<N, C>> + '_
+    where
+        Self: Sized,
+    {
+        self.evolve(|u| Snapshot {
+            generation: u.generation(),
+            cells: u.cells().collect(),
+        })
+    }
+
+    /// number of live cells
+    fn population(&self) -> usize;
+
+    /// the positions of all live cells, in no particular order
+    fn cells(&self) -> Box<dyn Iterator<Item = Vector<N, C>> + '_>;
+
+    /// None if there are no live cells
+    fn bounding_box(&self) -> Option<BoundingBox<N, C>> {
+        self.cells().fold(None, |bb, c| {
+            let mut bb = bb.unwrap_or(BoundingBox { min: c, max: c });
+            for (a, x) in c.iter().enumerate() {
+                bb.min[a] = bb.min[a].min(*x);
+                bb.max[a] = bb.max[a].max(*x);
+            }
+            Some(bb)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use crate::hashlife::HashLife;
+    use crate::tiled::Tiled;
+    use crate::topology::Topology;
+    use crate::Life;
+
+    // every backend, each test runs against all of them
+    // the patterns are kept in 0..32 so they fit into the bounded ones
+    fn backends() -> Vec<Box<dyn Universe<2>>> {
+        let mut torus = Life::new();
+        torus.set_topology(Topology::torus([32, 32]));
+
+        vec![
+            Box::new(Life::new()),
+            Box::new(torus),
+            Box::new(Grid::new([32, 32])),
+            Box::new(HashLife::new()),
+            Box::new(Tiled::new()),
+        ]
+    }
+
+    fn cells(u: &dyn Universe<2>) -> HashSet<Vector<2>> {
+        u.cells().collect()
+    }
+
+    #[test]
+    fn rod() {
+        for mut life in backends() {
+            life.create([5, 6]);
+            life.create([5, 5]);
+            life.create([5, 4]);
+
+            let cells0 = cells(life.as_ref());
+
+            life.cycle();
+            assert_eq!(
+                cells(life.as_ref()),
+                HashSet::from([[4, 5], [5, 5], [6, 5]])
+            );
+            life.cycle();
+
+            assert_eq!(cells0, cells(life.as_ref()));
+        }
+    }
+
+    #[test]
+    fn square() {
+        for mut life in backends() {
+            life.create([5, 5]);
+            life.create([5, 6]);
+            life.create([6, 5]);
+            life.create([6, 6]);
+
+            let cells0 = cells(life.as_ref());
+
+            life.cycle();
+
+            assert_eq!(cells0, cells(life.as_ref()));
+        }
+    }
+
+    #[test]
+    fn mutation() {
+        for mut life in backends() {
+            assert_eq!(life.bounding_box(), None);
+
+            life.create([3, 7]);
+            life.create([9, 2]);
+            life.create([4, 4]);
+            life.kill([4, 4]);
+            life.kill([20, 20]);
+
+            assert!(life.get(&[3, 7]));
+            assert!(!life.get(&[4, 4]));
+            assert_eq!(life.population(), 2);
+
+            let bb = life.bounding_box().unwrap();
+            assert_eq!(bb.min, [3, 2]);
+            assert_eq!(bb.max, [9, 7]);
+            assert_eq!(bb.size(), [7, 6]);
+        }
+    }
+
+    #[test]
+    fn generations() {
+        for mut life in backends() {
+            life.create([5, 6]);
+            life.create([5, 5]);
+            life.create([5, 4]);
+            let cells0 = cells(life.as_ref());
+
+            life.step_n(4);
+            assert_eq!(life.generation(), 4);
+            assert_eq!(cells(life.as_ref()), cells0);
+
+            life.cycle();
+            assert_eq!(life.generation(), 5);
+            assert_eq!(life.bounding_box().unwrap().size(), [3, 1]);
+        }
+    }
+
+    #[test]
+    fn summaries() {
+        let mut life = Life::<2>::new();
+
+        // a glider moves one cell diagonally every 4 generations
+        let glider = [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]];
+        for c in glider {
+            life.create(c);
+        }
+
+        let moved: HashSet<_> = glider.iter().map(|&[x, y]| [x + 3, y + 3]).collect();
+        let snapshot = life.snapshots().find(|s| s.cells == moved).unwrap();
+        assert_eq!(snapshot.generation, 12);
+        assert_eq!(life.generation(), 12);
+
+        let summaries: Vec<_> = life.summaries().take(5).collect();
+        assert!(summaries.iter().all(|s| s.population == 5));
+        assert_eq!(summaries[0].bounding_box.unwrap().min, [3, 3]);
+        assert_eq!(summaries[4].generation, 16);
+        assert_eq!(summaries[4].bounding_box.unwrap().min, [4, 4]);
+        assert_eq!(life.generation(), 16);
+    }
+}
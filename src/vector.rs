@@ -0,0 +1,39 @@
+//! positions and offsets and arithmetic on them
+
+use crate::coord::Coord;
+
+/// vector type, represents coordinates in N dimensions
+pub type Vector<const N: usize, C = i32> = [C; N];
+
+/// vector addition, panics if a coordinate overflows
+pub fn vec_add<const N: usize, C: Coord>(a: &Vector<N, C>, b: &Vector<N, C>) -> Vector<N, C> {
+    let mut res = *a;
+
+    for (i, val) in b.iter().enumerate() {
+        res[i] = res[i].checked_add(*val).expect("coordinate overflow");
+    }
+
+    res
+}
+
+/// vector subtraction, panics if a coordinate overflows
+pub fn vec_sub<const N: usize, C: Coord>(a: &Vector<N, C>, b: &Vector<N, C>) -> Vector<N, C> {
+    let mut res = *a;
+
+    for (i, val) in b.iter().enumerate() {
+        res[i] = res[i].checked_sub(*val).expect("coordinate overflow");
+    }
+
+    res
+}
+
+/// convert between coordinate types, None if a coordinate doesn't fit
+pub fn vec_cast<const N: usize, A: Coord, B: Coord>(v: &Vector<N, A>) -> Option<Vector<N, B>> {
+    let mut res = [B::default(); N];
+
+    for (i, val) in v.iter().enumerate() {
+        res[i] = B::from_i64(val.to_i64())?;
+    }
+
+    Some(res)
+}